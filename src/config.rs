@@ -0,0 +1,372 @@
+//! The configuration file and its named profiles.
+//!
+//! The file lives at `~/.config/pomodoro/config.toml` and provides defaults for
+//! every `pomodoro run` setting:
+//!
+//! ```toml
+//! [defaults]
+//! cycles = 6
+//!
+//! [profiles.deep-work]
+//! focus = "50m"
+//! break-min = "10m"
+//! long-break = "30m"
+//! ```
+//!
+//! Settings are layered with [`RunSettings::or`]: command-line flags win over
+//! `POMODORO_*` environment variables, which win over the selected profile,
+//! which wins over `[defaults]`, which wins over the built-in defaults.
+
+use crate::duration::{format_duration, parse_duration};
+use crate::format::TimeFormat;
+use crate::hooks::Hooks;
+use crate::notify::Notifications;
+use crate::session::SessionConfig;
+use crate::sound::Sounds;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Exit code for an unreadable or malformed config file, following sysexits' EX_CONFIG.
+pub const EXIT_CONFIG: i32 = 78;
+
+/// Written by `pomodoro config edit` when there is no config file yet.
+pub const TEMPLATE: &str = r#"# Pomodoro configuration
+#
+# Values here are defaults for `pomodoro run`. Command-line flags and
+# POMODORO_* environment variables take precedence, then the profile picked
+# with --profile, then [defaults]. Durations accept 90s, 25m, 1h30m, 1:30:00
+# or plain minutes.
+
+[defaults]
+# focus = "25m"
+# break-min = "5m"
+# long-break = "15m"
+# cycles = 4
+# long-every = 4
+# time-format = "clock"
+
+# [profiles.deep-work]
+# focus = "50m"
+# break-min = "10m"
+# long-break = "30m"
+
+# Shell commands run when phases start and end, with details of the session in
+# POMODORO_* environment variables. A hook that fails or runs past the timeout
+# is reported, but the timer carries on.
+# [hooks]
+# on-focus-start = "makoctl mode -a do-not-disturb"
+# on-focus-end = "makoctl mode -r do-not-disturb"
+# on-break-start = "loginctl lock-session"
+# on-break-end = ""
+# on-session-complete = ""
+# on-cancel = ""
+# timeout = "10s"
+
+# Desktop notifications when a phase ends. In the templates, {ended} is the
+# phase that ended, {next} the one starting, {duration} its length and
+# {cycle}/{cycles} the position in the session.
+# [notifications]
+# enabled = true
+# urgency = "normal"            # low, normal or critical
+# summary = "{ended} done"
+# body = "{next} for {duration} (session {cycle}/{cycles})"
+# actions = true                # "Start" and "Skip" buttons
+# expire = "10s"
+
+# Sounds at phase boundaries: "bell" for the terminal bell, a path to a WAV or
+# OGG file, or "" for silence. The ending sounds default to the bell.
+# [sounds]
+# focus-end = "bell"
+# break-end = "~/sounds/gong.ogg"
+# session-complete = "bell"
+# tick = "~/sounds/tick.wav"    # every second of focus; off by default
+# volume = 80                   # percent
+# player = "mpv --no-video"     # played with the file as its last argument
+# backend = "system"            # system, none, or file (a line per cue in `sink`)
+"#;
+
+/// Every `pomodoro run` setting, each one optional so layers can be merged.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RunSettings {
+    #[serde(
+        default,
+        with = "duration_setting",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub focus: Option<u64>,
+    #[serde(
+        default,
+        with = "duration_setting",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub break_min: Option<u64>,
+    #[serde(
+        default,
+        with = "duration_setting",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub long_break: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycles: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub long_every: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_format: Option<TimeFormat>,
+    /// Keys that are not settings, kept so they can be reported
+    #[serde(flatten, skip_serializing)]
+    pub unknown: BTreeMap<String, toml::Value>,
+}
+
+impl RunSettings {
+    /// Fill every setting missing from `self` with the one from `lower`.
+    pub fn or(self, lower: RunSettings) -> RunSettings {
+        RunSettings {
+            focus: self.focus.or(lower.focus),
+            break_min: self.break_min.or(lower.break_min),
+            long_break: self.long_break.or(lower.long_break),
+            cycles: self.cycles.or(lower.cycles),
+            long_every: self.long_every.or(lower.long_every),
+            time_format: self.time_format.or(lower.time_format),
+            unknown: BTreeMap::new(),
+        }
+    }
+
+    /// The session schedule, with built-in defaults for anything still missing.
+    pub fn session_config(&self) -> SessionConfig {
+        let defaults = SessionConfig::default();
+        SessionConfig {
+            focus_secs: self.focus.unwrap_or(defaults.focus_secs),
+            break_secs: self.break_min.unwrap_or(defaults.break_secs),
+            long_break_secs: self.long_break.unwrap_or(defaults.long_break_secs),
+            cycles: self.cycles.unwrap_or(defaults.cycles),
+            long_every: self.long_every.unwrap_or(defaults.long_every),
+        }
+    }
+
+    /// Every setting filled in, built-in defaults included, e.g. for `config show`.
+    pub fn resolved(&self) -> RunSettings {
+        let session = self.session_config();
+        RunSettings {
+            focus: Some(session.focus_secs),
+            break_min: Some(session.break_secs),
+            long_break: Some(session.long_break_secs),
+            cycles: Some(session.cycles),
+            long_every: Some(session.long_every),
+            time_format: Some(self.time_format.unwrap_or_default()),
+            unknown: BTreeMap::new(),
+        }
+    }
+}
+
+// Durations are written like on the command line ("25m", "1:30:00") or as a
+// plain integer number of minutes, and printed back in the compact unit form
+pub(crate) mod duration_setting {
+    use super::{format_duration, parse_duration};
+    use serde::{Deserializer, Serializer, de};
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(secs: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match secs {
+            Some(secs) => serializer.serialize_str(&format_duration(*secs)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u64>, D::Error> {
+        deserializer.deserialize_any(DurationVisitor).map(Some)
+    }
+
+    struct DurationVisitor;
+
+    impl de::Visitor<'_> for DurationVisitor {
+        type Value = u64;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a duration such as \"25m\", \"1h30m\" or a number of minutes")
+        }
+
+        fn visit_i64<E: de::Error>(self, minutes: i64) -> Result<u64, E> {
+            let minutes = u64::try_from(minutes)
+                .map_err(|_| E::custom(format!("duration {minutes} must not be negative")))?;
+            self.visit_u64(minutes)
+        }
+
+        fn visit_u64<E: de::Error>(self, minutes: u64) -> Result<u64, E> {
+            parse_duration(&minutes.to_string()).map_err(E::custom)
+        }
+
+        fn visit_str<E: de::Error>(self, text: &str) -> Result<u64, E> {
+            parse_duration(text)
+                .map_err(|err| E::custom(format!("invalid duration '{text}': {err}")))
+        }
+    }
+}
+
+/// The parsed contents of the config file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ConfigFile {
+    /// Settings applied to every run
+    #[serde(default)]
+    pub defaults: RunSettings,
+    /// Named sets of settings, picked with `--profile`
+    #[serde(default)]
+    pub profiles: BTreeMap<String, RunSettings>,
+    /// Commands to run when phases start and end
+    #[serde(default)]
+    pub hooks: Hooks,
+    /// Desktop notifications at phase changes
+    #[serde(default)]
+    pub notifications: Notifications,
+    /// Sounds at phase boundaries
+    #[serde(default)]
+    pub sounds: Sounds,
+    /// Top-level keys that are not sections, kept so they can be reported
+    #[serde(flatten)]
+    pub unknown: BTreeMap<String, toml::Value>,
+}
+
+/// A config file that could not be used.
+#[derive(Debug)]
+pub enum ConfigFileError {
+    /// The file exists but could not be read
+    Io { path: PathBuf, source: io::Error },
+    /// The file is not valid TOML or a value has the wrong type or format
+    Parse {
+        path: PathBuf,
+        source: Box<toml::de::Error>,
+    },
+    /// `--profile` named a profile the file does not define
+    UnknownProfile { name: String, path: Option<PathBuf> },
+}
+
+impl ConfigFileError {
+    /// The process exit code the CLI should use for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ConfigFileError::UnknownProfile { .. } => crate::validate::EXIT_INVALID,
+            _ => EXIT_CONFIG,
+        }
+    }
+}
+
+impl fmt::Display for ConfigFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigFileError::Io { path, source } => {
+                write!(f, "cannot read {}: {source}", path.display())
+            }
+            ConfigFileError::Parse { path, source } => {
+                write!(f, "invalid config file {}: {source}", path.display())
+            }
+            ConfigFileError::UnknownProfile {
+                name,
+                path: Some(path),
+            } => {
+                write!(f, "no profile '{name}' in {}", path.display())
+            }
+            ConfigFileError::UnknownProfile { name, path: None } => {
+                write!(f, "no profile '{name}': there is no config file")
+            }
+        }
+    }
+}
+
+impl Error for ConfigFileError {}
+
+impl ConfigFile {
+    /// Read and parse the file at `path`; a missing file is simply `None`.
+    pub fn load(path: &Path) -> Result<Option<ConfigFile>, ConfigFileError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => {
+                return Err(ConfigFileError::Io {
+                    path: path.to_path_buf(),
+                    source,
+                });
+            }
+        };
+        ConfigFile::parse(&text)
+            .map(Some)
+            .map_err(|source| ConfigFileError::Parse {
+                path: path.to_path_buf(),
+                source: Box::new(source),
+            })
+    }
+
+    pub fn parse(text: &str) -> Result<ConfigFile, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// `[defaults]` with the named profile (if any) layered on top.
+    pub fn settings(&self, profile: Option<&str>) -> Option<RunSettings> {
+        let defaults = self.defaults.clone();
+        match profile {
+            None => Some(defaults),
+            Some(name) => Some(self.profiles.get(name)?.clone().or(defaults)),
+        }
+    }
+
+    /// Dotted paths of every key the file defines but pomodoro does not know.
+    pub fn unknown_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.unknown.keys().cloned().collect();
+        keys.extend(
+            self.defaults
+                .unknown
+                .keys()
+                .map(|key| format!("defaults.{key}")),
+        );
+        keys.extend(self.hooks.unknown.keys().map(|key| format!("hooks.{key}")));
+        keys.extend(
+            self.notifications
+                .unknown
+                .keys()
+                .map(|key| format!("notifications.{key}")),
+        );
+        keys.extend(
+            self.sounds
+                .unknown
+                .keys()
+                .map(|key| format!("sounds.{key}")),
+        );
+        for (name, profile) in &self.profiles {
+            keys.extend(
+                profile
+                    .unknown
+                    .keys()
+                    .map(|key| format!("profiles.{name}.{key}")),
+            );
+        }
+        keys
+    }
+}
+
+/// Load the config file at `path` (if given) and pick out the settings for `profile`.
+///
+/// Returns the settings along with any unknown keys found in the file.
+pub fn load_settings(
+    path: Option<&Path>,
+    profile: Option<&str>,
+) -> Result<(RunSettings, Vec<String>), ConfigFileError> {
+    // No config file behaves like an empty one
+    let file = match path {
+        Some(path) => ConfigFile::load(path)?.unwrap_or_default(),
+        None => ConfigFile::default(),
+    };
+    let settings = file
+        .settings(profile)
+        .ok_or_else(|| ConfigFileError::UnknownProfile {
+            name: profile.unwrap_or_default().to_string(),
+            path: path.map(Path::to_path_buf),
+        })?;
+    Ok((settings, file.unknown_keys()))
+}
@@ -0,0 +1,545 @@
+//! The background daemon and the protocol its clients speak.
+//!
+//! `pomodoro daemon` runs sessions on behalf of short-lived client commands
+//! (`pomodoro start`, `pause`, `status`, ...) that talk to it over a Unix domain
+//! socket. The protocol is line-delimited JSON: the client writes one request
+//! object per line and reads back exactly one response line for each. A
+//! connection may carry any number of requests.
+//!
+//! Every request names its `command`; `start` also carries the session to run:
+//!
+//! ```text
+//! {"command":"start","config":{"focus_secs":1500,"break_secs":300,"long_break_secs":900,"cycles":4,"long_every":4},"profile":"deep-work"}
+//! {"command":"pause"}
+//! {"command":"resume"}
+//! {"command":"skip"}
+//! {"command":"stop"}
+//! {"command":"status"}
+//! {"command":"interrupt","kind":"external","note":"Slack ping"}
+//! {"command":"void","reason":"fire drill"}
+//! ```
+//!
+//! Every response has `ok`. Failures add an `error` message, and the answer to
+//! `status` adds a `session` object unless the daemon is idle:
+//!
+//! ```text
+//! {"ok":true}
+//! {"ok":false,"error":"no session is running"}
+//! {"ok":true,"session":{"cycle":2,"cycles":4,"phase":"focus","remaining":754,"total":1500,"paused":false}}
+//! ```
+//!
+//! `resume` on an idle daemon picks up the interrupted session saved in the
+//! state file, the way `pomodoro resume` does in the foreground.
+
+use crate::format::TimeFormat;
+use crate::history::Labels;
+use crate::session::{
+    Control, Event, Fanout, Interruption, Observer, Position, Session, SessionConfig,
+};
+use crate::state::StateFile;
+use crate::status::Status;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, DirBuilder, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::DirBuilderExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Exit code when the daemon cannot be reached, following sysexits' EX_UNAVAILABLE.
+pub const EXIT_UNAVAILABLE: i32 = 69;
+
+/// How long a client waits for the daemon to answer.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A session for the daemon to run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    pub config: SessionConfig,
+    /// The config profile the settings came from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// How the client displays time, kept for sessions resumed in the foreground
+    #[serde(default)]
+    pub time_format: TimeFormat,
+    /// The task the focus phases are attributed to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    /// The number of that task
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<u64>,
+    /// The project and tags the session is filed under
+    #[serde(flatten)]
+    pub labels: Labels,
+}
+
+/// One line sent by a client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    /// Start a new session; fails if one is already running
+    Start(Plan),
+    Pause,
+    /// Resume the paused session, or the interrupted one if none is running
+    Resume,
+    /// End the current phase and move on to the next
+    Skip,
+    /// Cancel the running session
+    Stop,
+    Status,
+    /// Log an interruption of the current focus phase
+    Interrupt {
+        kind: Interruption,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+    },
+    /// Void the current focus phase and run it again
+    Void {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+}
+
+impl Request {
+    // The session control this request maps to, if any
+    fn control(&self) -> Option<Control> {
+        match self {
+            Request::Pause => Some(Control::Pause),
+            Request::Resume => Some(Control::Resume),
+            Request::Skip => Some(Control::Skip),
+            Request::Interrupt { kind, note } => Some(Control::Interrupt(*kind, note.clone())),
+            Request::Void { reason } => Some(Control::Void(reason.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// One line sent back by the daemon.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The running session, in answer to `status`; absent when idle
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<Status>,
+}
+
+impl Response {
+    fn ok(session: Option<Status>) -> Self {
+        Response {
+            ok: true,
+            error: None,
+            session,
+        }
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Response {
+            ok: false,
+            error: Some(message.into()),
+            session: None,
+        }
+    }
+}
+
+/// A connection to the daemon.
+pub struct Client {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Client {
+    pub fn connect(socket: &Path) -> io::Result<Client> {
+        let stream = UnixStream::connect(socket)?;
+        stream.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+        Ok(Client {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    /// Send one request and wait for its response.
+    pub fn send(&mut self, request: &Request) -> io::Result<Response> {
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+
+        let mut answer = String::new();
+        if self.reader.read_line(&mut answer)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the daemon closed the connection",
+            ));
+        }
+        Ok(serde_json::from_str(&answer)?)
+    }
+}
+
+/// Builds the observers for each session the daemon starts, e.g. the history recorder.
+///
+/// Observers that take input, such as notification actions, send it to the
+/// session through the given sender.
+pub type ObserverFactory = Box<dyn Fn(&Plan, &Sender<Control>) -> Fanout + Send + Sync>;
+
+/// Why the daemon could not start.
+#[derive(Debug)]
+pub enum DaemonError {
+    /// Another daemon holds the lock file
+    AlreadyRunning { lock: PathBuf },
+    /// The socket or lock file could not be set up
+    Io { path: PathBuf, source: io::Error },
+}
+
+impl fmt::Display for DaemonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DaemonError::AlreadyRunning { lock } => {
+                write!(f, "a daemon is already running (lock {})", lock.display())
+            }
+            DaemonError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+        }
+    }
+}
+
+impl Error for DaemonError {}
+
+/// The daemon: a listening socket plus the session it is running, if any.
+pub struct Daemon {
+    listener: UnixListener,
+    socket: PathBuf,
+    // Held for as long as the daemon lives; the lock goes away with the process
+    _lock: File,
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    observers: ObserverFactory,
+    state_file: Option<StateFile>,
+    running: Mutex<Option<Running>>,
+}
+
+// A session running on its own thread
+struct Running {
+    controls: Sender<Control>,
+    cancelled: Arc<AtomicBool>,
+    status: Arc<Mutex<Option<Status>>>,
+    handle: JoinHandle<()>,
+}
+
+impl Daemon {
+    /// Take the lock next to `socket` and start listening on it.
+    ///
+    /// Fails with [`DaemonError::AlreadyRunning`] if another daemon has the lock.
+    /// A socket file left behind by a daemon that died is replaced.
+    pub fn bind(socket: impl Into<PathBuf>) -> Result<Daemon, DaemonError> {
+        let socket = socket.into();
+        let io_error = |path: &Path| {
+            let path = path.to_path_buf();
+            move |source| DaemonError::Io { path, source }
+        };
+
+        // The socket accepts commands, so keep its directory private
+        if let Some(dir) = socket.parent() {
+            DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(dir)
+                .map_err(io_error(dir))?;
+        }
+
+        let lock_path = socket.with_extension("lock");
+        let mut lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(io_error(&lock_path))?;
+        // SAFETY: flock on a file descriptor we own
+        if unsafe { libc::flock(lock.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+            let err = io::Error::last_os_error();
+            return Err(if err.kind() == io::ErrorKind::WouldBlock {
+                DaemonError::AlreadyRunning { lock: lock_path }
+            } else {
+                DaemonError::Io {
+                    path: lock_path,
+                    source: err,
+                }
+            });
+        }
+        // The pid is only informational; the lock itself is what counts
+        lock.set_len(0)
+            .and_then(|()| writeln!(lock, "{}", std::process::id()))
+            .map_err(io_error(&lock_path))?;
+
+        // With the lock held, any socket file still there is stale
+        match fs::remove_file(&socket) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(io_error(&socket)(err));
+            }
+            _ => {}
+        }
+        let listener = UnixListener::bind(&socket).map_err(io_error(&socket))?;
+
+        Ok(Daemon {
+            listener,
+            socket,
+            _lock: lock,
+            inner: Arc::new(Inner {
+                observers: Box::new(|_, _| Fanout::new()),
+                state_file: None,
+                running: Mutex::new(None),
+            }),
+        })
+    }
+
+    /// Attach the observers built by `factory` to every session the daemon runs.
+    pub fn with_observers(mut self, factory: ObserverFactory) -> Self {
+        self.inner_mut().observers = factory;
+        self
+    }
+
+    /// Resume the session saved in `file` when asked to resume while idle.
+    pub fn with_state_file(mut self, file: StateFile) -> Self {
+        self.inner_mut().state_file = Some(file);
+        self
+    }
+
+    pub fn socket(&self) -> &Path {
+        &self.socket
+    }
+
+    /// A handle that shuts the daemon down from another thread, e.g. on a signal.
+    pub fn shutdown_handle(&self) -> Shutdown {
+        Shutdown {
+            inner: Arc::clone(&self.inner),
+            socket: self.socket.clone(),
+        }
+    }
+
+    // Only called while building, before any connection holds a reference
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.inner).expect("daemon is not serving yet")
+    }
+
+    /// Serve clients until the listening socket fails; each connection gets a thread.
+    pub fn serve(&self) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let inner = Arc::clone(&self.inner);
+            thread::spawn(move || inner.serve_client(stream));
+        }
+        Ok(())
+    }
+}
+
+/// Stops a daemon's session and takes its socket away; see [`Daemon::shutdown_handle`].
+pub struct Shutdown {
+    inner: Arc<Inner>,
+    socket: PathBuf,
+}
+
+impl Shutdown {
+    /// Cancel the running session, waiting for it to be recorded, and remove the
+    /// socket so clients stop connecting. The caller then exits the process.
+    pub fn shutdown(&self) {
+        self.inner.handle(Request::Stop);
+        fs::remove_file(&self.socket).ok();
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        fs::remove_file(&self.socket).ok();
+    }
+}
+
+impl Inner {
+    // Answer requests from one client until it hangs up
+    fn serve_client(&self, stream: UnixStream) {
+        let Ok(mut writer) = stream.try_clone() else {
+            return;
+        };
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str(&line) {
+                Ok(request) => self.handle(request),
+                Err(err) => Response::error(format!("invalid request: {err}")),
+            };
+            // A response only holds strings, numbers and booleans, so this cannot fail
+            let mut answer = serde_json::to_string(&response).unwrap_or_default();
+            answer.push('\n');
+            if writer.write_all(answer.as_bytes()).is_err() {
+                break;
+            }
+        }
+    }
+
+    fn handle(&self, request: Request) -> Response {
+        let mut running = self.running.lock().unwrap_or_else(PoisonError::into_inner);
+        // A session that ran to its end leaves the daemon idle
+        if running.as_ref().is_some_and(|r| r.handle.is_finished()) {
+            *running = None;
+        }
+
+        let Some(session) = running.as_ref() else {
+            let started = match request {
+                Request::Start(plan) => self.start(plan, None),
+                Request::Resume => self.resume_saved(),
+                Request::Status => return Response::ok(None),
+                _ => return Response::error("no session is running"),
+            };
+            return match started {
+                Ok(session) => {
+                    *running = Some(session);
+                    Response::ok(None)
+                }
+                Err(message) => Response::error(message),
+            };
+        };
+
+        if let Some(control) = request.control() {
+            // The session would ignore it, but the client should hear why
+            let status = *session
+                .status
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if status.is_some_and(|status| status.phase.is_break()) {
+                match request {
+                    Request::Interrupt { .. } => {
+                        return Response::error(
+                            "interruptions are only logged during focus phases",
+                        );
+                    }
+                    Request::Void { .. } => {
+                        return Response::error("only focus phases can be voided");
+                    }
+                    _ => {}
+                }
+            }
+            session.controls.send(control).ok();
+            return Response::ok(None);
+        }
+        match request {
+            Request::Start(_) => Response::error("a session is already running; stop it first"),
+            Request::Status => Response::ok(
+                *session
+                    .status
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner),
+            ),
+            _ => {
+                // Stop: wait for the session to record its cancellation before answering
+                if let Some(session) = running.take() {
+                    session.cancelled.store(true, Ordering::SeqCst);
+                    session.handle.join().ok();
+                }
+                Response::ok(None)
+            }
+        }
+    }
+
+    // Run `plan` on a new thread, from `position` if given
+    fn start(&self, plan: Plan, position: Option<Position>) -> Result<Running, String> {
+        plan.config.validate().map_err(|err| err.to_string())?;
+
+        let (controls, rx) = mpsc::channel();
+        let status = Arc::new(Mutex::new(None));
+        let mut observers = (self.observers)(&plan, &controls);
+        observers.push(StatusTracker {
+            cycles: plan.config.cycles,
+            status: Arc::clone(&status),
+        });
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut session = Session::new(plan.config).with_cancel_flag(Arc::clone(&cancelled));
+        if let Some(position) = position {
+            session = session.starting_at(position);
+        }
+        let handle = thread::spawn(move || {
+            session.run(&rx, &mut observers);
+        });
+
+        Ok(Running {
+            controls,
+            cancelled,
+            status,
+            handle,
+        })
+    }
+
+    // Carry on with the session in the state file, counting the time since it stopped
+    fn resume_saved(&self) -> Result<Running, String> {
+        const NOTHING: &str = "no session is running and there is none to resume";
+        let Some(file) = &self.state_file else {
+            return Err(NOTHING.into());
+        };
+        let state = match file.load() {
+            Ok(Some(state)) => state,
+            Ok(None) => return Err(NOTHING.into()),
+            Err(err) => return Err(format!("cannot read {}: {err}", file.path().display())),
+        };
+        if state.is_running() {
+            return Err(format!(
+                "the session is still running (process {})",
+                state.pid
+            ));
+        }
+        let Some(position) = state.position_at(Local::now()) else {
+            file.remove().ok();
+            return Err("the interrupted session would have finished by now".into());
+        };
+        let plan = Plan {
+            config: state.config,
+            profile: state.profile,
+            time_format: state.time_format,
+            task: state.task,
+            task_id: state.task_id,
+            labels: state.labels,
+        };
+        self.start(plan, Some(position))
+    }
+}
+
+// Keeps the latest status of a running session for `status` requests
+struct StatusTracker {
+    cycles: u64,
+    status: Arc<Mutex<Option<Status>>>,
+}
+
+impl Observer for StatusTracker {
+    fn on_event(&mut self, event: &Event) {
+        let latest = match *event {
+            Event::Tick {
+                cycle,
+                phase,
+                remaining,
+                total,
+                paused,
+            } => Some(Status {
+                cycle,
+                cycles: self.cycles,
+                phase,
+                remaining,
+                total,
+                paused,
+            }),
+            Event::SessionCompleted | Event::Cancelled { .. } => None,
+            Event::PhaseStarted { .. }
+            | Event::PhaseCompleted { .. }
+            | Event::Interrupted { .. } => return,
+        };
+        *self.status.lock().unwrap_or_else(PoisonError::into_inner) = latest;
+    }
+}
@@ -0,0 +1,159 @@
+//! Parsing and printing of human-friendly durations.
+//!
+//! Accepted forms, all converted to whole seconds:
+//!
+//! - plain numbers are minutes, for compatibility with the original flags: `25`
+//! - unit suffixes, largest first and in any combination: `90s`, `25m`, `1h30m`, `1h5m30s`
+//! - clock notation: `25:00` (M:SS) or `1:30:00` (H:MM:SS)
+
+use std::error::Error;
+use std::fmt;
+
+/// Why a duration string could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseDurationError {
+    /// Nothing to parse
+    Empty,
+    /// A number was expected but something else was found
+    InvalidNumber(String),
+    /// A unit other than h, m or s, or units out of order / repeated
+    BadUnit(String),
+    /// Minutes or seconds of 60 or more in clock notation
+    OutOfRange(String),
+    /// The duration does not fit in a u64 number of seconds
+    Overflow,
+}
+
+impl fmt::Display for ParseDurationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseDurationError::Empty => write!(f, "empty duration"),
+            ParseDurationError::InvalidNumber(part) => {
+                write!(
+                    f,
+                    "'{part}' is not a number (try 25, 90s, 1h30m or 1:30:00)"
+                )
+            }
+            ParseDurationError::BadUnit(unit) => write!(
+                f,
+                "unexpected unit '{unit}' (use h, m and s from largest to smallest, e.g. 1h30m)"
+            ),
+            ParseDurationError::OutOfRange(part) => {
+                write!(
+                    f,
+                    "'{part}' must be below 60 in clock notation (e.g. 1:30:00)"
+                )
+            }
+            ParseDurationError::Overflow => write!(f, "duration is too large"),
+        }
+    }
+}
+
+impl Error for ParseDurationError {}
+
+/// Parse a duration in any of the accepted forms into seconds.
+pub fn parse_duration(input: &str) -> Result<u64, ParseDurationError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(ParseDurationError::Empty);
+    }
+
+    if input.contains(':') {
+        parse_clock(input)
+    } else if input.bytes().all(|b| b.is_ascii_digit()) {
+        // A bare number keeps its historical meaning of minutes
+        scale(parse_number(input)?, 60)
+    } else {
+        parse_units(input)
+    }
+}
+
+// "M:SS" or "H:MM:SS"
+fn parse_clock(input: &str) -> Result<u64, ParseDurationError> {
+    let parts: Vec<&str> = input.split(':').collect();
+    if parts.len() > 3 {
+        return Err(ParseDurationError::InvalidNumber(input.to_string()));
+    }
+
+    // The leading part is unbounded (e.g. 90:00), the others must be below 60
+    let mut total: u64 = parse_number(parts[0])?;
+    for part in &parts[1..] {
+        let value = parse_number(part)?;
+        if value >= 60 {
+            return Err(ParseDurationError::OutOfRange(part.to_string()));
+        }
+        total = add(scale(total, 60)?, value)?;
+    }
+    Ok(total)
+}
+
+// "1h30m", "90s", "2h5m10s"; each unit at most once, largest first
+fn parse_units(input: &str) -> Result<u64, ParseDurationError> {
+    let mut total: u64 = 0;
+    let mut rest = input;
+    let mut smallest_so_far = u64::MAX; // Seconds per unit of the last unit seen
+
+    while !rest.is_empty() {
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        let (number, after) = rest.split_at(digits);
+        let unit_len = after.bytes().take_while(|b| !b.is_ascii_digit()).count();
+        let (unit, after) = after.split_at(unit_len);
+
+        let value = parse_number(number)?;
+        let per_unit = match unit.trim() {
+            "h" => 3600,
+            "m" => 60,
+            "s" => 1,
+            "" => {
+                return Err(ParseDurationError::BadUnit(format!(
+                    "{number} (missing unit)"
+                )));
+            }
+            other => return Err(ParseDurationError::BadUnit(other.to_string())),
+        };
+        if per_unit >= smallest_so_far {
+            return Err(ParseDurationError::BadUnit(unit.to_string()));
+        }
+        smallest_so_far = per_unit;
+
+        total = add(total, scale(value, per_unit)?)?;
+        rest = after;
+    }
+    Ok(total)
+}
+
+fn parse_number(part: &str) -> Result<u64, ParseDurationError> {
+    if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(ParseDurationError::InvalidNumber(part.to_string()));
+    }
+    // All digits, so the only way to fail is a number too big for u64
+    part.parse().map_err(|_| ParseDurationError::Overflow)
+}
+
+fn scale(value: u64, factor: u64) -> Result<u64, ParseDurationError> {
+    value
+        .checked_mul(factor)
+        .ok_or(ParseDurationError::Overflow)
+}
+
+fn add(a: u64, b: u64) -> Result<u64, ParseDurationError> {
+    a.checked_add(b).ok_or(ParseDurationError::Overflow)
+}
+
+/// Print seconds in the compact unit form accepted by [`parse_duration`].
+///
+/// Example: 5400 becomes "1h30m", 90 becomes "1m30s" and 0 becomes "0s".
+pub fn format_duration(total_secs: u64) -> String {
+    let (h, m, s) = (total_secs / 3600, total_secs % 3600 / 60, total_secs % 60);
+    let mut out = String::new();
+    if h > 0 {
+        out.push_str(&format!("{h}h"));
+    }
+    if m > 0 {
+        out.push_str(&format!("{m}m"));
+    }
+    if s > 0 || out.is_empty() {
+        out.push_str(&format!("{s}s"));
+    }
+    out
+}
@@ -0,0 +1,136 @@
+//! Rendering of remaining time in the styles the user can pick with `--time-format`.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::fmt;
+use std::str::FromStr;
+
+/// How remaining time is shown by the renderers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// 25:00, or 1:30:00 from an hour up
+    #[default]
+    Clock,
+    /// 25m, 1h30m, or 45s in the last minute
+    Compact,
+    /// 24 min 59 s
+    Verbose,
+    /// How much of the phase is done, e.g. 40%
+    Percent,
+}
+
+impl TimeFormat {
+    /// Every style, in the order they are listed in help and error messages.
+    pub const ALL: [TimeFormat; 4] = [
+        TimeFormat::Clock,
+        TimeFormat::Compact,
+        TimeFormat::Verbose,
+        TimeFormat::Percent,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TimeFormat::Clock => "clock",
+            TimeFormat::Compact => "compact",
+            TimeFormat::Verbose => "verbose",
+            TimeFormat::Percent => "percent",
+        }
+    }
+}
+
+impl fmt::Display for TimeFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TimeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TimeFormat::ALL
+            .into_iter()
+            .find(|format| format.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = TimeFormat::ALL.iter().map(|f| f.name()).collect();
+                format!(
+                    "unknown time format '{s}' (expected one of: {})",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+// Stored by name in the config file, e.g. time-format = "compact"
+impl Serialize for TimeFormat {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeFormat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Format `remaining` seconds of a phase that is `total` seconds long.
+pub fn format_time(remaining: u64, total: u64, format: TimeFormat) -> String {
+    match format {
+        TimeFormat::Clock => fmt_clock(remaining),
+        TimeFormat::Compact => fmt_compact(remaining),
+        TimeFormat::Verbose => fmt_verbose(remaining),
+        TimeFormat::Percent => fmt_percent(remaining, total),
+    }
+}
+
+/// M:SS below an hour and H:MM:SS from an hour up.
+///
+/// Example: 125 seconds becomes "2:05" and 9000 seconds becomes "2:30:00".
+pub fn fmt_clock(total_secs: u64) -> String {
+    let h = total_secs / 3600;
+    let m = total_secs % 3600 / 60;
+    let s = total_secs % 60;
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m}:{s:02}") // Zero-padded seconds (e.g., "5:03" not "5:3")
+    }
+}
+
+// Whole minutes, rounded up so a timer with 24:59 left still reads "25m";
+// only the last minute is shown in seconds
+fn fmt_compact(total_secs: u64) -> String {
+    if total_secs < 60 {
+        return format!("{total_secs}s");
+    }
+    let minutes = total_secs.div_ceil(60);
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{m}m"),
+        (h, 0) => format!("{h}h"),
+        (h, m) => format!("{h}h{m}m"),
+    }
+}
+
+fn fmt_verbose(total_secs: u64) -> String {
+    let h = total_secs / 3600;
+    let m = total_secs % 3600 / 60;
+    let s = total_secs % 60;
+    if h > 0 {
+        format!("{h} h {m} min {s} s")
+    } else if m > 0 {
+        format!("{m} min {s} s")
+    } else {
+        format!("{s} s")
+    }
+}
+
+// Share of the phase that is already done; an empty phase counts as finished
+fn fmt_percent(remaining: u64, total: u64) -> String {
+    if total == 0 {
+        return "100%".to_string();
+    }
+    let done = total.saturating_sub(remaining);
+    format!("{}%", (done as u128 * 100 / total as u128))
+}
@@ -0,0 +1,396 @@
+//! The session history: one record per focus or break phase, kept on disk.
+//!
+//! Records are appended as JSON lines to `~/.local/share/pomodoro/history.jsonl`
+//! as soon as each phase ends, and synced to disk right away, so a crash loses
+//! at most the phase that was running.
+
+use crate::session::{Event, Interruption, Observer, Outcome, Phase};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// The project and tags a session's time is filed under, for reports to
+/// filter and group by.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Labels {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl Labels {
+    /// Labels with surrounding spaces trimmed, blanks dropped and each tag kept once.
+    pub fn new(project: Option<String>, tags: Vec<String>) -> Self {
+        let project = project
+            .map(|project| project.trim().to_string())
+            .filter(|project| !project.is_empty());
+        let mut kept: Vec<String> = Vec::new();
+        for tag in tags {
+            let tag = tag.trim();
+            if !tag.is_empty() && !kept.iter().any(|kept| kept.eq_ignore_ascii_case(tag)) {
+                kept.push(tag.to_string());
+            }
+        }
+        Labels {
+            project,
+            tags: kept,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.project.is_none() && self.tags.is_empty()
+    }
+
+    /// Whether these labels have everything `wanted` asks for: its project, if it
+    /// names one, and every one of its tags. Names are compared ignoring case.
+    pub fn includes(&self, wanted: &Labels) -> bool {
+        let project = match (&wanted.project, &self.project) {
+            (None, _) => true,
+            (Some(wanted), Some(project)) => wanted.eq_ignore_ascii_case(project),
+            (Some(_), None) => false,
+        };
+        project
+            && wanted
+                .tags
+                .iter()
+                .all(|wanted| self.tags.iter().any(|tag| tag.eq_ignore_ascii_case(wanted)))
+    }
+}
+
+// Shown as `backend #review #oncall`
+impl fmt::Display for Labels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut words: Vec<String> = self.project.iter().cloned().collect();
+        words.extend(self.tags.iter().map(|tag| format!("#{tag}")));
+        f.write_str(&words.join(" "))
+    }
+}
+
+/// An interruption logged during a focus phase.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InterruptionRecord {
+    /// Wall-clock time it was logged
+    pub at: DateTime<Local>,
+    pub kind: Interruption,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// One finished (or cancelled) phase.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PhaseRecord {
+    /// Wall-clock time the phase started
+    pub started_at: DateTime<Local>,
+    /// Wall-clock time the phase ended
+    pub ended_at: DateTime<Local>,
+    pub phase: Phase,
+    /// The focus phase number within its session (1-based)
+    pub cycle: u64,
+    /// The length the phase was scheduled for, in seconds
+    pub planned_secs: u64,
+    /// How long the phase actually ran, in seconds, not counting pauses
+    pub actual_secs: u64,
+    /// The phase carried on where a cancelled run of it stopped; that run has a
+    /// record of its own with the seconds before, so this is not a new attempt
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub resumed: bool,
+    pub outcome: Outcome,
+    /// Why a voided phase was voided
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// How many times the phase was paused
+    #[serde(default)]
+    pub pauses: u32,
+    /// The config profile the session was started with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// The task a focus phase was spent on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    /// The number of that task; records from before tasks had one only name it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<u64>,
+    /// The project and tags of the session
+    #[serde(flatten)]
+    pub labels: Labels,
+    /// Interruptions logged while the phase ran, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interruptions: Vec<InterruptionRecord>,
+    /// How focused a completed pomodoro was, from 1 to 5, when the user said
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    /// What got done in a completed pomodoro, in the user's words
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// The user's own account of a completed pomodoro; either half may be skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reflection {
+    /// How focused it was, from 1 (scattered) to 5 (deep focus)
+    pub rating: Option<u8>,
+    /// What got done
+    pub note: Option<String>,
+}
+
+/// The history file.
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    path: PathBuf,
+}
+
+impl HistoryStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        HistoryStore { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one record and make sure it reached the disk.
+    pub fn append(&self, record: &PhaseRecord) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.path)?;
+        // If a crash left a partial last line, start on a fresh one so this record
+        // does not get glued onto (and lost with) the broken one
+        if file.metadata()?.len() > 0 {
+            let mut last = [0u8; 1];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                line.insert(0, '\n');
+            }
+        }
+        // One write per record keeps concurrent appends from interleaving
+        file.write_all(line.as_bytes())?;
+        file.sync_data()
+    }
+
+    /// Replace the latest record equal to `old` with `new`, such as to add what
+    /// the user said about a pomodoro that was written as it ended.
+    ///
+    /// Writes to a temporary file and renames it over the history, so a crash
+    /// leaves either the old or the new file, never a mix.
+    pub fn replace(&self, old: &PhaseRecord, new: &PhaseRecord) -> io::Result<()> {
+        let text = fs::read_to_string(&self.path)?;
+        let mut lines: Vec<String> = text.lines().map(String::from).collect();
+        let line = lines
+            .iter_mut()
+            .rev()
+            .find(|line| serde_json::from_str::<PhaseRecord>(line).is_ok_and(|r| r == *old))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "the record is gone"))?;
+        *line = serde_json::to_string(new)?;
+
+        let tmp = self.path.with_extension("jsonl.tmp");
+        let mut file = File::create(&tmp)?;
+        for line in &lines {
+            writeln!(file, "{line}")?;
+        }
+        file.sync_data()?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    /// Every record in the file, oldest first; a missing file is an empty history.
+    ///
+    /// Lines that cannot be parsed (such as one cut short by a crash) are skipped.
+    pub fn load(&self) -> io::Result<Vec<PhaseRecord>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let mut records = Vec::new();
+        for line in BufReader::new(file).lines() {
+            if let Ok(record) = serde_json::from_str(&line?) {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+}
+
+// Asks the user how a completed pomodoro went
+type Reflect = Box<dyn FnMut(&PhaseRecord) -> Reflection + Send>;
+
+/// Observer that writes a [`PhaseRecord`] to the history at every phase boundary.
+pub struct HistoryRecorder {
+    store: HistoryStore,
+    profile: Option<String>,
+    task: Option<String>,
+    task_id: Option<u64>,
+    labels: Labels,
+    // Start time and planned length of the phase that is running, and the
+    // seconds of it an earlier, cancelled run already recorded
+    current: Option<(DateTime<Local>, u64, u64)>,
+    // Pauses so far in the running phase, and whether it is paused right now
+    pauses: u32,
+    paused: bool,
+    interruptions: Vec<InterruptionRecord>,
+    // Asks for a reflection on each completed pomodoro
+    reflect: Option<Reflect>,
+    reported_error: bool,
+}
+
+impl HistoryRecorder {
+    pub fn new(store: HistoryStore, profile: Option<String>) -> Self {
+        HistoryRecorder {
+            store,
+            profile,
+            task: None,
+            task_id: None,
+            labels: Labels::default(),
+            current: None,
+            pauses: 0,
+            paused: false,
+            interruptions: Vec::new(),
+            reflect: None,
+            reported_error: false,
+        }
+    }
+
+    /// Attribute the focus phases to `task`, the name of task number `id`.
+    pub fn with_task(mut self, task: Option<String>, id: Option<u64>) -> Self {
+        self.task = task;
+        self.task_id = id;
+        self
+    }
+
+    /// File every phase under `labels`.
+    pub fn with_labels(mut self, labels: Labels) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Ask `reflect` how each completed pomodoro went, as it ends, and add the
+    /// answer to its record.
+    ///
+    /// The record is written first, so it is kept even if the session stops
+    /// while the user is answering. The session waits for the answer before the
+    /// next phase starts; observers placed before this one have already had the
+    /// end of the pomodoro.
+    pub fn with_reflection(
+        mut self,
+        reflect: Box<dyn FnMut(&PhaseRecord) -> Reflection + Send>,
+    ) -> Self {
+        self.reflect = Some(reflect);
+        self
+    }
+
+    fn record(
+        &mut self,
+        cycle: u64,
+        phase: Phase,
+        outcome: Outcome,
+        elapsed: u64,
+        reason: Option<String>,
+    ) {
+        let Some((started_at, planned_secs, recorded)) = self.current.take() else {
+            return;
+        };
+        let record = PhaseRecord {
+            started_at,
+            ended_at: Local::now(),
+            phase,
+            cycle,
+            planned_secs,
+            actual_secs: elapsed.saturating_sub(recorded),
+            resumed: recorded > 0,
+            outcome,
+            reason,
+            pauses: self.pauses,
+            profile: self.profile.clone(),
+            task: self.task.clone().filter(|_| phase == Phase::Focus),
+            task_id: self.task_id.filter(|_| phase == Phase::Focus),
+            labels: self.labels.clone(),
+            interruptions: std::mem::take(&mut self.interruptions),
+            rating: None,
+            note: None,
+        };
+        let result = self.store.append(&record);
+        self.report(result);
+
+        if let Some(reflect) = &mut self.reflect
+            && phase == Phase::Focus
+            && outcome == Outcome::Completed
+        {
+            let Reflection { rating, note } = reflect(&record);
+            if rating.is_some() || note.is_some() {
+                let reflected = PhaseRecord {
+                    rating,
+                    note,
+                    ..record.clone()
+                };
+                let result = self.store.replace(&record, &reflected);
+                self.report(result);
+            }
+        }
+    }
+
+    // A full disk should not stop the timer; say so once and carry on
+    fn report(&mut self, result: io::Result<()>) {
+        if let Err(err) = result
+            && !self.reported_error
+        {
+            self.reported_error = true;
+            eprintln!(
+                "\nwarning: cannot write history to {}: {err}",
+                self.store.path().display()
+            );
+        }
+    }
+}
+
+impl Observer for HistoryRecorder {
+    fn on_event(&mut self, event: &Event) {
+        match *event {
+            Event::PhaseStarted {
+                duration, reported, ..
+            } => {
+                self.current = Some((Local::now(), duration, reported));
+                self.pauses = 0;
+                self.paused = false;
+                self.interruptions.clear();
+            }
+            Event::Tick { paused, .. } => {
+                if paused && !self.paused {
+                    self.pauses += 1;
+                }
+                self.paused = paused;
+            }
+            Event::PhaseCompleted {
+                cycle,
+                phase,
+                outcome,
+                elapsed,
+                ref reason,
+            } => self.record(cycle, phase, outcome, elapsed, reason.clone()),
+            Event::Cancelled {
+                cycle,
+                phase,
+                elapsed,
+            } => self.record(cycle, phase, Outcome::Cancelled, elapsed, None),
+            Event::Interrupted { kind, ref note, .. } => {
+                self.interruptions.push(InterruptionRecord {
+                    at: Local::now(),
+                    kind,
+                    note: note.clone(),
+                })
+            }
+            Event::SessionCompleted => {}
+        }
+    }
+}
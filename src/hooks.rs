@@ -0,0 +1,386 @@
+//! User commands run at the start and end of phases, from the `[hooks]` section.
+//!
+//! Each hook is a shell command run with `sh -c`, with the details of the session
+//! in `POMODORO_*` environment variables. Hooks run one at a time on a worker
+//! thread so a slow one never holds up the countdown; one that runs past its
+//! timeout is killed, and failures are reported on stderr without stopping the
+//! timer.
+
+use crate::config::duration_setting;
+use crate::session::{Event, Observer, Outcome, Phase};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How long a hook may run when the config does not say.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often to check whether a running hook has finished.
+const WAIT_POLL: Duration = Duration::from_millis(20);
+
+/// How long to wait for the rest of a failed hook's stderr once it has exited;
+/// a child it left running in the background can keep the pipe open for good.
+const STDERR_GRACE: Duration = Duration::from_millis(200);
+
+/// How much of a failed hook's stderr to keep; only its last line is reported.
+const STDERR_TAIL: usize = 4096;
+
+/// The points in a session where a hook can run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    FocusStart,
+    FocusEnd,
+    BreakStart,
+    BreakEnd,
+    SessionComplete,
+    Cancel,
+}
+
+impl Hook {
+    /// The key naming this hook in the `[hooks]` section.
+    pub fn name(self) -> &'static str {
+        match self {
+            Hook::FocusStart => "on-focus-start",
+            Hook::FocusEnd => "on-focus-end",
+            Hook::BreakStart => "on-break-start",
+            Hook::BreakEnd => "on-break-end",
+            Hook::SessionComplete => "on-session-complete",
+            Hook::Cancel => "on-cancel",
+        }
+    }
+}
+
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The `[hooks]` section of the config file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Hooks {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_focus_start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_focus_end: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_break_start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_break_end: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_session_complete: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_cancel: Option<String>,
+    /// How long each hook may run, in seconds
+    #[serde(
+        default,
+        with = "duration_setting",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub timeout: Option<u64>,
+    /// Keys that are not hooks, kept so they can be reported
+    #[serde(flatten, skip_serializing)]
+    pub unknown: BTreeMap<String, toml::Value>,
+}
+
+impl Hooks {
+    /// The command configured for `hook`; an empty one counts as none.
+    pub fn command(&self, hook: Hook) -> Option<&str> {
+        let command = match hook {
+            Hook::FocusStart => &self.on_focus_start,
+            Hook::FocusEnd => &self.on_focus_end,
+            Hook::BreakStart => &self.on_break_start,
+            Hook::BreakEnd => &self.on_break_end,
+            Hook::SessionComplete => &self.on_session_complete,
+            Hook::Cancel => &self.on_cancel,
+        };
+        command
+            .as_deref()
+            .filter(|command| !command.trim().is_empty())
+    }
+
+    /// Whether no hook is configured at all.
+    pub fn is_empty(&self) -> bool {
+        use Hook::*;
+        [
+            FocusStart,
+            FocusEnd,
+            BreakStart,
+            BreakEnd,
+            SessionComplete,
+            Cancel,
+        ]
+        .into_iter()
+        .all(|hook| self.command(hook).is_none())
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout.map_or(DEFAULT_TIMEOUT, Duration::from_secs)
+    }
+}
+
+/// How a hook run went wrong.
+#[derive(Debug)]
+pub enum HookError {
+    /// The shell could not be started
+    Spawn(std::io::Error),
+    /// The command exited unsuccessfully; holds the last line it wrote to stderr
+    Failed(ExitStatus, String),
+    /// The command ran past the timeout and was killed
+    TimedOut(Duration),
+}
+
+impl fmt::Display for HookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookError::Spawn(err) => write!(f, "cannot start sh: {err}"),
+            HookError::Failed(status, stderr) if stderr.is_empty() => write!(f, "{status}"),
+            HookError::Failed(status, stderr) => write!(f, "{status} ({stderr})"),
+            HookError::TimedOut(timeout) => {
+                write!(f, "killed after {}s", timeout.as_secs_f64())
+            }
+        }
+    }
+}
+
+impl std::error::Error for HookError {}
+
+/// Run `command` with `sh -c` and the given extra environment, waiting at most `timeout`.
+///
+/// The command gets its own process group, so on timeout anything it started is
+/// killed along with it.
+pub fn run_hook(
+    command: &str,
+    env: &[(String, String)],
+    timeout: Duration,
+) -> Result<(), HookError> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()
+        .map_err(HookError::Spawn)?;
+
+    // Drain stderr on the side so a chatty hook cannot fill the pipe and stall;
+    // the reader is never joined, it ends whenever the last writer closes the pipe
+    let (chunks, output) = mpsc::channel();
+    if let Some(mut stderr) = child.stderr.take() {
+        thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            loop {
+                match stderr.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) if chunks.send(buf[..n].to_vec()).is_err() => break,
+                    Ok(_) => {}
+                }
+            }
+        });
+    }
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => thread::sleep(WAIT_POLL),
+            _ => {
+                // SAFETY: signalling the process group we created for the hook
+                unsafe {
+                    libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+                }
+                child.wait().ok();
+                return Err(HookError::TimedOut(timeout));
+            }
+        }
+    };
+    if status.success() {
+        return Ok(());
+    }
+    // Whatever stderr the hook wrote before exiting, without waiting on its children
+    let mut bytes = Vec::new();
+    let grace = Instant::now() + STDERR_GRACE;
+    while let Some(left) = grace
+        .checked_duration_since(Instant::now())
+        .filter(|left| !left.is_zero())
+        && let Ok(chunk) = output.recv_timeout(left)
+    {
+        bytes.extend(chunk);
+        bytes.drain(..bytes.len().saturating_sub(STDERR_TAIL));
+    }
+    let stderr = String::from_utf8_lossy(&bytes);
+    let last_line = stderr.lines().rfind(|line| !line.trim().is_empty());
+    Err(HookError::Failed(
+        status,
+        last_line.unwrap_or_default().trim().to_string(),
+    ))
+}
+
+// One hook waiting to run, with its environment
+struct Job {
+    hook: Hook,
+    command: String,
+    env: Vec<(String, String)>,
+}
+
+/// Observer that runs the configured hooks at the matching session events.
+///
+/// Dropping it waits for hooks that are still queued, so a final
+/// `on-session-complete` is not lost when the process exits right after.
+pub struct HookRunner {
+    hooks: Hooks,
+    cycles: u64,
+    profile: Option<String>,
+    jobs: Option<Sender<Job>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl HookRunner {
+    pub fn new(hooks: Hooks, cycles: u64, profile: Option<String>) -> Self {
+        let timeout = hooks.timeout();
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let worker = thread::spawn(move || {
+            for job in queue {
+                if let Err(err) = run_hook(&job.command, &job.env, timeout) {
+                    eprintln!("\nwarning: hook {} failed: {err}", job.hook);
+                }
+            }
+        });
+        HookRunner {
+            hooks,
+            cycles,
+            profile,
+            jobs: Some(jobs),
+            worker: Some(worker),
+        }
+    }
+
+    // Queue `hook` if it is configured, describing the session in its environment
+    fn fire(&self, hook: Hook, details: &[(&str, String)]) {
+        let (Some(command), Some(jobs)) = (self.hooks.command(hook), &self.jobs) else {
+            return;
+        };
+        let mut env = vec![
+            ("POMODORO_EVENT".to_string(), hook.name().to_string()),
+            ("POMODORO_CYCLES".to_string(), self.cycles.to_string()),
+        ];
+        if let Some(profile) = &self.profile {
+            env.push(("POMODORO_PROFILE".to_string(), profile.clone()));
+        }
+        env.extend(
+            details
+                .iter()
+                .map(|(key, value)| (format!("POMODORO_{key}"), value.clone())),
+        );
+        jobs.send(Job {
+            hook,
+            command: command.to_string(),
+            env,
+        })
+        .ok();
+    }
+}
+
+// The value of POMODORO_PHASE and POMODORO_OUTCOME, matching the history file
+fn phase_id(phase: Phase) -> String {
+    match phase {
+        Phase::Focus => "focus",
+        Phase::Break => "break",
+        Phase::LongBreak => "long_break",
+    }
+    .to_string()
+}
+
+fn outcome_id(outcome: Outcome) -> String {
+    match outcome {
+        Outcome::Completed => "completed",
+        Outcome::Skipped => "skipped",
+        Outcome::Restarted => "restarted",
+        Outcome::Cancelled => "cancelled",
+        Outcome::Voided => "voided",
+    }
+    .to_string()
+}
+
+impl Observer for HookRunner {
+    fn on_event(&mut self, event: &Event) {
+        match *event {
+            Event::PhaseStarted {
+                cycle,
+                phase,
+                duration,
+                ..
+            } => {
+                let hook = if phase.is_break() {
+                    Hook::BreakStart
+                } else {
+                    Hook::FocusStart
+                };
+                self.fire(
+                    hook,
+                    &[
+                        ("PHASE", phase_id(phase)),
+                        ("CYCLE", cycle.to_string()),
+                        ("DURATION", duration.to_string()),
+                    ],
+                );
+            }
+            Event::PhaseCompleted {
+                cycle,
+                phase,
+                outcome,
+                elapsed,
+                ref reason,
+            } => {
+                let hook = if phase.is_break() {
+                    Hook::BreakEnd
+                } else {
+                    Hook::FocusEnd
+                };
+                let mut vars = vec![
+                    ("PHASE", phase_id(phase)),
+                    ("CYCLE", cycle.to_string()),
+                    ("ELAPSED", elapsed.to_string()),
+                    ("OUTCOME", outcome_id(outcome)),
+                ];
+                if let Some(reason) = reason {
+                    vars.push(("REASON", reason.clone()));
+                }
+                self.fire(hook, &vars);
+            }
+            Event::SessionCompleted => self.fire(Hook::SessionComplete, &[]),
+            Event::Cancelled {
+                cycle,
+                phase,
+                elapsed,
+            } => self.fire(
+                Hook::Cancel,
+                &[
+                    ("PHASE", phase_id(phase)),
+                    ("CYCLE", cycle.to_string()),
+                    ("ELAPSED", elapsed.to_string()),
+                ],
+            ),
+            Event::Tick { .. } | Event::Interrupted { .. } => {}
+        }
+    }
+}
+
+impl Drop for HookRunner {
+    fn drop(&mut self) {
+        // Closing the queue lets the worker finish what is left and exit
+        self.jobs.take();
+        if let Some(worker) = self.worker.take() {
+            worker.join().ok();
+        }
+    }
+}
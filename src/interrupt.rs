@@ -0,0 +1,63 @@
+//! Interruptions and voids sent from another terminal with `pomodoro interrupt`
+//! and `pomodoro void`.
+//!
+//! The daemon takes them over its socket. A session in the foreground has no
+//! socket, so the command appends the [`Control`] to an inbox file next to the
+//! daemon's socket and sends the session's process `SIGUSR2`; the session then
+//! takes everything in the inbox and applies it.
+
+use crate::session::Control;
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+
+/// The inbox file.
+#[derive(Debug, Clone)]
+pub struct Inbox {
+    path: PathBuf,
+}
+
+impl Inbox {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Inbox { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Leave `control` for the session to take.
+    pub fn push(&self, control: &Control) -> io::Result<()> {
+        // Notes can be private, so keep the directory to ourselves as the daemon does
+        if let Some(dir) = self.path.parent() {
+            DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        }
+        let mut line = serde_json::to_string(control)?;
+        line.push('\n');
+        // One write per entry keeps concurrent pushes from interleaving
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())
+    }
+
+    /// Empty the inbox, returning the controls in it, oldest first.
+    ///
+    /// The file is moved aside before it is read, so an interruption pushed in
+    /// the meantime waits for the next call instead of being lost.
+    pub fn take(&self) -> io::Result<Vec<Control>> {
+        let taken = self.path.with_extension("jsonl.taken");
+        match fs::rename(&self.path, &taken) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            result => result?,
+        }
+        let text = fs::read_to_string(&taken);
+        fs::remove_file(&taken).ok();
+        Ok(text?
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
@@ -0,0 +1,50 @@
+//! Timer engine behind the `pomodoro` CLI.
+//!
+//! The scheduling of focus phases, short breaks and long breaks lives in
+//! [`Session`], which reports progress as typed [`Event`]s to an [`Observer`].
+//! The command-line binary is just one consumer that renders those events;
+//! other tools can embed the same engine and react to them however they like.
+
+pub mod clock;
+pub mod config;
+pub mod daemon;
+pub mod duration;
+pub mod format;
+pub mod history;
+pub mod hooks;
+pub mod interrupt;
+pub mod notify;
+pub mod paths;
+pub mod render;
+pub mod session;
+pub mod sound;
+pub mod state;
+pub mod stats;
+pub mod status;
+pub mod tasks;
+pub mod title;
+pub mod tui;
+pub mod validate;
+
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use config::{ConfigFile, RunSettings};
+pub use daemon::{Client, Daemon, Plan, Request, Response};
+pub use duration::{format_duration, parse_duration};
+pub use format::{TimeFormat, format_time};
+pub use history::{
+    HistoryRecorder, HistoryStore, InterruptionRecord, Labels, PhaseRecord, Reflection,
+};
+pub use hooks::{HookRunner, Hooks};
+pub use notify::{Notifications, Notifier};
+pub use render::JsonRenderer;
+pub use session::{
+    Control, Event, Fanout, Interruption, Observer, Outcome, Phase, Position, Session,
+    SessionConfig,
+};
+pub use sound::{SoundPlayer, Sounds};
+pub use state::{SessionState, StateFile, StateSaver};
+pub use status::{Status, StatusTemplate};
+pub use tasks::{Task, TaskStore};
+pub use title::TerminalTitle;
+pub use tui::TuiRenderer;
+pub use validate::{ConfigError, ConfigWarning};
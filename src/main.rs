@@ -1,9 +1,31 @@
 // Import necessary crates for command-line parsing, I/O operations, threading, time handling, and signal handling
-use clap::{Parser, Subcommand};
-use std::io::{self, Write};
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::thread;
+use chrono::Local;
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use pomodoro_cli::config::{self, EXIT_CONFIG};
+use pomodoro_cli::daemon::EXIT_UNAVAILABLE;
+use pomodoro_cli::interrupt::Inbox;
+use pomodoro_cli::stats::{self, DateRange, GroupBy};
+use pomodoro_cli::tasks::{self, TaskError};
+use pomodoro_cli::{
+    Client, ConfigFile, Control, Daemon, Event, Fanout, HistoryRecorder, HistoryStore, HookRunner,
+    Interruption, JsonRenderer, Labels, Notifier, Observer, Outcome, Phase, PhaseRecord, Plan,
+    Position, Reflection, Request, Response, RunSettings, Session, SoundPlayer, StateFile,
+    StateSaver, Status, StatusTemplate, Task, TaskStore, TerminalTitle, TimeFormat, TuiRenderer,
+    format_duration, format_time, parse_duration, paths, title, tui,
+};
+use signal_hook::consts::{SIGCONT, SIGHUP, SIGINT, SIGTERM, SIGTSTP, SIGUSR1, SIGUSR2};
+use signal_hook::iterator::{Handle as SignalsHandle, Signals};
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
 // Define the main CLI structure using clap's derive macros
@@ -17,186 +39,1501 @@ struct Cli {
 }
 
 // Define the available subcommands for the CLI
-// Currently we only have one subcommand "run", but this enum structure allows
-// for easy addition of more commands in the future (like "status", "config", etc.)
+// Each variant is one `pomodoro <command>`; new commands get a variant here
+// and a branch in `main`
 #[derive(Subcommand)]
 enum Command {
     /// Run a Pomodoro cycle
     Run {
-        /// Focus minutes - how long each focus session should last
-        /// Default is 25 minutes, which is the traditional Pomodoro technique duration
-        #[arg(short = 'f', long, default_value_t = 25)]
-        focus: u64,
-        /// Break minutes - how long each break should last
-        /// Default is 5 minutes for short breaks between focus sessions
-        #[arg(short = 'b', long, default_value_t = 5)]
-        break_min: u64,
-        /// Number of focus sessions before a long break (we'll use later)
-        /// Default is 4 cycles, following the traditional Pomodoro technique
-        #[arg(short = 'c', long, default_value_t = 4)]
-        cycles: u64,
-        /// Long break minutes
-        /// Default is 15 minutes, which is longer than regular breaks for better rest
-        #[arg(long = "long-break", default_value_t = 15)]
-        long_break: u64,
-        /// Take a long break every N focus sessions
-        /// Default is every 4 sessions, aligning with traditional Pomodoro cycles
-        #[arg(long = "long-every", default_value_t = 4)]
-        long_every: u64,
+        #[command(flatten)]
+        args: RunArgs,
+        #[command(flatten)]
+        display: DisplayArgs,
     },
+    /// Resume the daemon's paused session, or pick up an interrupted one
+    Resume {
+        #[command(flatten)]
+        display: DisplayArgs,
+    },
+    /// Run sessions in the background, controlled by the commands below
+    Daemon(DaemonArgs),
+    /// Start a session in the daemon
+    Start(RunArgs),
+    /// Pause the daemon's session
+    Pause,
+    /// Skip the rest of the daemon's current phase
+    Skip,
+    /// Cancel the daemon's session
+    Stop,
+    /// Log an interruption of the running focus phase, in the daemon or in the foreground
+    Interrupt(InterruptArgs),
+    /// Void the running focus phase: it is recorded as voided and starts again
+    Void {
+        /// Why, e.g. "fire drill"
+        reason: Option<String>,
+    },
+    /// Show where the running session is, in the daemon or in the foreground
+    Status(StatusArgs),
+    /// Show, edit or check the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Report on recorded sessions (today by default)
+    Stats(StatsArgs),
+    /// Manage the tasks pomodoros are attributed to
+    Task {
+        #[command(subcommand)]
+        action: TaskAction,
+    },
+}
+
+// Settings for a run. Every one of them is optional on the command line: anything
+// not given here falls back to the POMODORO_* environment variable, then the chosen
+// profile, then [defaults] in the config file, then the built-in default
+#[derive(Args)]
+struct RunArgs {
+    /// Focus duration - how long each focus session should last
+    /// Accepts 90s, 25m, 1h30m, 1:30:00 or plain minutes; default is 25 minutes,
+    /// which is the traditional Pomodoro technique duration
+    #[arg(short = 'f', long, env = "POMODORO_FOCUS", value_parser = parse_duration)]
+    focus: Option<u64>,
+    /// Break duration - how long each break should last
+    /// Same syntax as --focus; default is 5 minutes for short breaks between focus sessions
+    #[arg(short = 'b', long, env = "POMODORO_BREAK", value_parser = parse_duration)]
+    break_min: Option<u64>,
+    /// Number of focus sessions in the cycle
+    /// Default is 4 cycles, following the traditional Pomodoro technique
+    #[arg(short = 'c', long, env = "POMODORO_CYCLES")]
+    cycles: Option<u64>,
+    /// Long break duration
+    /// Same syntax as --focus; default is 15 minutes, which is longer than regular
+    /// breaks for better rest
+    #[arg(long = "long-break", env = "POMODORO_LONG_BREAK", value_parser = parse_duration)]
+    long_break: Option<u64>,
+    /// Take a long break every N focus sessions
+    /// Default is every 4 sessions, aligning with traditional Pomodoro cycles
+    #[arg(long = "long-every", env = "POMODORO_LONG_EVERY")]
+    long_every: Option<u64>,
+    /// How to show the remaining time: clock (25:00), compact (25m),
+    /// verbose (24 min 59 s) or percent (percentage of the phase completed)
+    #[arg(long = "time-format", env = "POMODORO_TIME_FORMAT")]
+    time_format: Option<TimeFormat>,
+    /// Named profile from the config file to take defaults from
+    #[arg(short = 'p', long, env = "POMODORO_PROFILE")]
+    profile: Option<String>,
+    /// Attribute the focus phases to this task, by number or name; a new name
+    /// adds the task
+    #[arg(short, long)]
+    task: Option<String>,
+    /// File the session's time under this project, for `pomodoro stats`
+    #[arg(long)]
+    project: Option<String>,
+    /// Tag the session's time; repeat for several tags
+    #[arg(long = "tag", value_name = "TAG")]
+    tags: Vec<String>,
+}
+
+impl RunArgs {
+    // The settings given on the command line or through the environment
+    fn settings(&self) -> RunSettings {
+        RunSettings {
+            focus: self.focus,
+            break_min: self.break_min,
+            long_break: self.long_break,
+            cycles: self.cycles,
+            long_every: self.long_every,
+            time_format: self.time_format,
+            ..RunSettings::default()
+        }
+    }
+
+    // Layer these settings over the config file to get the ones to run with
+    // Problems with the config file end the process with the matching exit code
+    fn resolve(&self) -> RunSettings {
+        let path = paths::config_file();
+        match config::load_settings(path.as_deref(), self.profile.as_deref()) {
+            Ok((file_settings, unknown_keys)) => {
+                for key in unknown_keys {
+                    eprintln!("warning: unknown config key `{key}` (ignored)");
+                }
+                self.settings().or(file_settings)
+            }
+            Err(err) => fail(&err, err.exit_code()),
+        }
+    }
+
+    // The session to run: the resolved settings, checked to make sense
+    // Warnings are printed; an impossible schedule ends the process
+    fn plan(&self) -> Plan {
+        // Durations were already parsed into seconds, either by clap or from the config file
+        let settings = self.resolve();
+        let config = settings.session_config();
+        match config.validate() {
+            Ok(warnings) => {
+                for warning in warnings {
+                    eprintln!("warning: {warning}");
+                }
+            }
+            Err(err) => fail(&err, err.exit_code()),
+        }
+        let task = self.task.as_deref().map(task_for);
+        Plan {
+            config,
+            profile: self.profile.clone(),
+            time_format: settings.time_format.unwrap_or_default(),
+            task_id: task.as_ref().map(|task| task.id),
+            task: task.map(|task| task.name),
+            labels: Labels::new(self.project.clone(), self.tags.clone()),
+        }
+    }
 }
 
-// Helper function to format seconds into MM:SS format for display
-// This makes the countdown timer more readable by showing time in familiar format
-// Example: 125 seconds becomes "2:05"
-fn fmt_mm_ss(total_secs: u64) -> String {
-    let m: u64 = total_secs / 60; // Extract minutes by integer division
-    let s: u64 = total_secs % 60; // Extract remaining seconds using modulo operator
-    format!("{m}:{s:02}") // Format with zero-padded seconds (e.g., "5:03" not "5:3")
+// How a session running in the foreground shows its progress
+#[derive(Args)]
+struct DisplayArgs {
+    /// text: a live countdown line (plain lines when stdout is not a terminal);
+    /// json: one JSON object per event, for scripts
+    #[arg(short, long, value_enum, default_value_t = Output::Text, env = "POMODORO_OUTPUT")]
+    output: Output,
+    /// Take over the whole terminal with a big clock, progress bar and cycle timeline
+    #[arg(long, conflicts_with = "output")]
+    tui: bool,
+    /// After each completed pomodoro, ask how focused you were (1-5) and what
+    /// got done, to keep with it in the history
+    #[arg(long, env = "POMODORO_REFLECT")]
+    reflect: bool,
+    /// How long the reflection prompt waits for a key before moving on
+    #[arg(long, value_parser = parse_duration, default_value = "30s")]
+    reflect_timeout: u64,
 }
 
-// Setup signal handler for graceful cancellation with Ctrl+C
-// This function creates a shared atomic boolean that gets set to true when SIGINT is received
-// Returns an Arc<AtomicBool> that can be checked in loops to detect cancellation requests
-fn setup_signal_handler() -> Arc<AtomicBool> {
-    let cancelled = Arc::new(AtomicBool::new(false)); // Create shared cancellation flag
-    let cancelled_clone = Arc::clone(&cancelled); // Clone for the signal handler closure
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Output {
+    Text,
+    Json,
+}
 
-    // Register signal handler for SIGINT (Ctrl+C)
-    // This uses a closure that captures the cloned atomic boolean
-    ctrlc::set_handler(move || {
-        cancelled_clone.store(true, Ordering::SeqCst); // Set cancellation flag atomically
-        println!("\n\n⏹️  Cancelled by user. Goodbye!"); // Inform user of cancellation
-        std::process::exit(0); // Exit immediately on Ctrl+C for clean termination
-    })
-    .expect("Error setting Ctrl+C handler"); // Panic if we can't set up signal handling
+impl DisplayArgs {
+    // Print an informational line, such as the settings in use
+    // With JSON output it goes to stderr so stdout carries nothing but events
+    fn say(&self, line: &str) {
+        match self.output {
+            Output::Text => println!("{line}"),
+            Output::Json => eprintln!("{line}"),
+        }
+    }
+}
 
-    cancelled // Return the cancellation flag for use in countdown loops
+// Options for `pomodoro interrupt`
+#[derive(Args)]
+struct InterruptArgs {
+    /// What interrupted you, e.g. "Slack ping"
+    note: Option<String>,
+    /// Someone or something else interrupted you; otherwise it was your own thought
+    #[arg(short, long)]
+    external: bool,
 }
 
-// Main countdown function that displays a real-time timer with cancellation support
-// This function creates a visual countdown that updates every second and can be cancelled with Ctrl+C
-// It uses precise timing to avoid drift over long periods and respects cancellation requests
-fn countdown_secs(secs: u64, label: &str, cancelled: &Arc<AtomicBool>) -> bool {
-    let start: Instant = Instant::now(); // Record the exact moment we started counting
-    let mut tick: u64 = 0u64; // Track how many seconds have elapsed since start
+// Options for `pomodoro daemon`
+#[derive(Args)]
+struct DaemonArgs {
+    /// Start the daemon in the background and return once it is listening
+    #[arg(short, long)]
+    detach: bool,
+}
 
-    // Main countdown loop - runs once per second until time expires or cancellation
+// The `pomodoro config` subcommands
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the settings a run would use, after applying the config file
+    Show(RunArgs),
+    /// Open the config file in $VISUAL or $EDITOR, creating it if needed
+    Edit,
+    /// Check the config file for unknown keys and bad values
+    Validate,
+}
+
+// Options for `pomodoro status`, mostly for status bars
+#[derive(Args)]
+struct StatusArgs {
+    /// Print a custom line, e.g. '{phase} {remaining} {cycle}/{cycles}'
+    /// Placeholders: phase, remaining, elapsed, total, percent, cycle, cycles and
+    /// state; prints an empty line when no session is running
+    #[arg(long, conflicts_with = "json")]
+    format: Option<StatusTemplate>,
+    /// Print the status as a JSON object
+    #[arg(long)]
+    json: bool,
+    /// Keep printing one line per second
+    #[arg(short, long)]
+    watch: bool,
+    /// How to show times: clock, compact, verbose or percent
+    #[arg(long = "time-format", env = "POMODORO_TIME_FORMAT")]
+    time_format: Option<TimeFormat>,
+}
+
+// The `pomodoro task` subcommands
+#[derive(Subcommand)]
+enum TaskAction {
+    /// Add a task to attribute pomodoros to
+    Add {
+        name: String,
+        /// How many pomodoros the task should take
+        #[arg(short, long)]
+        estimate: Option<u32>,
+    },
+    /// List the open tasks with their estimated and actual pomodoros
+    List {
+        /// Include tasks that are done
+        #[arg(short, long)]
+        all: bool,
+        /// Print the tasks as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+        /// Only count pomodoros filed under this project
+        #[arg(long)]
+        project: Option<String>,
+        /// Only count pomodoros with this tag; repeat to require several
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+    },
+    /// Mark a task as done
+    Done {
+        /// Task number or name
+        task: String,
+    },
+    /// Set how many pomodoros a task should take
+    Estimate {
+        /// Task number or name
+        task: String,
+        pomodoros: u32,
+    },
+}
+
+// Options for `pomodoro stats`; at most one period can be picked
+#[derive(Args)]
+#[command(group(ArgGroup::new("period").multiple(false)))]
+struct StatsArgs {
+    /// Report on today
+    #[arg(long, group = "period")]
+    day: bool,
+    /// Report on the current week (Monday to Sunday)
+    #[arg(long, group = "period")]
+    week: bool,
+    /// Report on the current month
+    #[arg(long, group = "period")]
+    month: bool,
+    /// Report on a range of days: YYYY-MM-DD..YYYY-MM-DD, or a single YYYY-MM-DD
+    #[arg(long, group = "period", value_parser = DateRange::parse)]
+    range: Option<DateRange>,
+    /// Only count focus time filed under this project
+    #[arg(long)]
+    project: Option<String>,
+    /// Only count focus time with this tag; repeat to require several
+    #[arg(long = "tag", value_name = "TAG")]
+    tags: Vec<String>,
+    /// Split the focus time by project, tag or task
+    #[arg(long, value_name = "project|tag|task")]
+    by: Option<GroupBy>,
+    /// Print the report as JSON instead of a table
+    #[arg(long)]
+    json: bool,
+}
+
+// Print an error and exit with the given status code
+fn fail(err: &dyn Display, code: i32) -> ! {
+    eprintln!("error: {err}");
+    process::exit(code);
+}
+
+// Map a single key press to a countdown command
+// Unknown keys return None and are simply ignored by the listener
+fn key_to_control(key: u8) -> Option<Control> {
+    match key {
+        b'p' | b' ' => Some(Control::TogglePause),
+        b's' => Some(Control::Skip),
+        b'r' => Some(Control::Restart),
+        b'+' | b'=' => Some(Control::Extend),
+        b'-' | b'_' => Some(Control::Shorten),
+        b'i' => Some(Control::Interrupt(Interruption::Internal, None)),
+        b'e' => Some(Control::Interrupt(Interruption::External, None)),
+        b'v' => Some(Control::Void(None)),
+        _ => None,
+    }
+}
+
+// The terminal settings that were active before the key listener switched stdin to raw mode
+// Stored globally so the Ctrl+C handler can put the terminal back before exiting the process
+static SAVED_TERMIOS: Mutex<Option<libc::termios>> = Mutex::new(None);
+
+// Put the terminal back into the mode it was in before the key listener started
+// Safe to call more than once and when raw mode was never enabled
+fn restore_terminal() {
+    if let Ok(mut saved) = SAVED_TERMIOS.lock()
+        && let Some(original) = saved.take()
+    {
+        // SAFETY: `original` was filled in by tcgetattr on the same file descriptor
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &original);
+        }
+    }
+}
+
+// Background thread that reads single key presses from stdin while a countdown is running
+// Stdin is switched to raw (non-canonical, no echo) mode so keys arrive without Enter;
+// output processing and signal keys are left alone so Ctrl+C still raises SIGINT
+struct KeyListener {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+    lines: LineReader,
+}
+
+impl KeyListener {
+    // Start listening for keys and forward recognised ones to `controls`
+    // Returns None when stdin is not a terminal (piped input, cron, etc.)
+    fn start(controls: Sender<Control>) -> Option<KeyListener> {
+        if !io::stdin().is_terminal() {
+            return None;
+        }
+
+        // Read the current terminal settings so they can be restored afterwards
+        // SAFETY: termios is plain old data and tcgetattr fully initialises it on success
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return None;
+        }
+
+        // Disable line buffering and echo, and make reads return after a single byte
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        // SAFETY: `raw` is a valid termios derived from the current settings
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return None;
+        }
+        *SAVED_TERMIOS.lock().unwrap_or_else(|e| e.into_inner()) = Some(original);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = Arc::clone(&stop);
+        let lines = LineReader::default();
+        let capture = Arc::clone(&lines.keys);
+        let handle = thread::spawn(move || {
+            let mut pollfd = libc::pollfd {
+                fd: libc::STDIN_FILENO,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            // Poll with a short timeout so the thread notices `stop` promptly
+            while !stop_clone.load(Ordering::SeqCst) {
+                // SAFETY: pollfd points to a single valid pollfd struct
+                let ready = unsafe { libc::poll(&mut pollfd, 1, 100) };
+                if ready <= 0 || pollfd.revents & libc::POLLIN == 0 {
+                    continue;
+                }
+                let mut byte = [0u8; 1];
+                // SAFETY: reading at most one byte into a one-byte buffer
+                let n = unsafe { libc::read(libc::STDIN_FILENO, byte.as_mut_ptr().cast(), 1) };
+                if n <= 0 {
+                    break; // EOF or error: nothing more to listen to
+                }
+                // While a line is being read the keys are typing it, not controlling the timer
+                let typing = capture.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                if let Some(keys) = typing {
+                    keys.send(byte[0]).ok();
+                } else if let Some(control) = key_to_control(byte[0])
+                    && controls.send(control).is_err()
+                {
+                    break; // The countdown is gone, stop listening
+                }
+            }
+        });
+
+        Some(KeyListener {
+            stop,
+            handle: Some(handle),
+            lines,
+        })
+    }
+}
+
+// Reads a line typed at the terminal while the key listener owns stdin, by
+// having the listener hand it the keys for as long as the line is being read
+#[derive(Clone, Default)]
+struct LineReader {
+    keys: Arc<Mutex<Option<Sender<u8>>>>,
+}
+
+impl LineReader {
+    // Read a line, echoing it as it is typed (raw mode does not)
+    // Returns None when no key is pressed for `idle`, or the session is cancelled
+    fn read_line(&self, idle: Duration, cancelled: &AtomicBool) -> Option<String> {
+        let (tx, rx) = mpsc::channel();
+        *self.keys.lock().unwrap_or_else(|e| e.into_inner()) = Some(tx);
+        let mut line = Vec::new();
+        let mut deadline = Instant::now() + idle;
+        let typed = loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() || cancelled.load(Ordering::SeqCst) {
+                break None;
+            }
+            // Wake up now and then to notice a cancelled session
+            let Ok(byte) = rx.recv_timeout(left.min(Duration::from_millis(100))) else {
+                continue;
+            };
+            match byte {
+                b'\r' | b'\n' => break Some(String::from_utf8_lossy(&line).trim().to_string()),
+                // Backspace: drop the last character, with all the bytes it was encoded in
+                0x7f | 0x08 if !line.is_empty() => {
+                    while line.pop().is_some_and(|byte| byte & 0xc0 == 0x80) {}
+                    print!("\x08 \x08");
+                }
+                byte if byte >= 0x20 => {
+                    line.push(byte);
+                    io::stdout().write_all(&[byte]).ok();
+                }
+                _ => {}
+            }
+            io::stdout().flush().ok();
+            deadline = Instant::now() + idle; // Someone is typing, give them time
+        };
+        *self.keys.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        println!();
+        typed
+    }
+}
+
+// Ask how a completed pomodoro went: a 1-5 focus rating and a note on what got
+// done. Either can be skipped with Enter, and when no key is pressed for `idle`
+// the session just carries on, so nobody has to be there to answer
+fn ask_reflection(lines: &LineReader, idle: Duration, cancelled: &AtomicBool) -> Reflection {
+    let rating = loop {
+        print!(
+            "How focused were you, 1-5? (Enter to skip, {} to answer) ",
+            format_duration(idle.as_secs())
+        );
+        io::stdout().flush().ok();
+        let Some(answer) = lines.read_line(idle, cancelled) else {
+            return Reflection::default();
+        };
+        if answer.is_empty() {
+            break None;
+        }
+        match answer.parse() {
+            Ok(rating @ 1..=5) => break Some(rating),
+            _ => println!("Please answer with a number from 1 to 5"),
+        }
+    };
+    print!("What got done? ");
+    io::stdout().flush().ok();
+    let note = lines
+        .read_line(idle, cancelled)
+        .filter(|note| !note.is_empty());
+    Reflection { rating, note }
+}
+
+impl Drop for KeyListener {
+    // Stop the listener thread and restore the original terminal mode
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+        restore_terminal();
+    }
+}
+
+// Signals that stop a session; the process then exits with 128 + the signal
+// number, as shells report a process killed by it (130, 143 and 129)
+const STOP_SIGNALS: [i32; 3] = [SIGINT, SIGTERM, SIGHUP];
+
+// The stop signal that cancelled the session in the foreground, 0 while none has
+static CAUGHT: AtomicI32 = AtomicI32::new(0);
+
+// Handle signals while a session runs in the foreground, on a thread of their own
+// SIGINT, SIGTERM and SIGHUP set `cancelled`, so the session ends like any other
+// cancellation: the partial phase is recorded, the state saved and the terminal
+// restored as the observers are dropped. A second one exits straight away, in case
+// the first got stuck. SIGTSTP (Ctrl+Z) and SIGCONT pause and resume the timer
+// instead of the process, SIGUSR1 skips the current phase and SIGUSR2 says
+// `pomodoro interrupt` or `void` left controls in the inbox
+fn handle_signals(cancelled: Arc<AtomicBool>, controls: Sender<Control>) -> SignalsHandle {
+    let mut signals = Signals::new(
+        STOP_SIGNALS
+            .iter()
+            .chain(&[SIGTSTP, SIGCONT, SIGUSR1, SIGUSR2]),
+    )
+    .unwrap_or_else(|err| fail(&format!("cannot handle signals: {err}"), 1));
+    let handle = signals.handle();
+    let inbox = Inbox::new(paths::interrupts_file());
+    // Whatever an earlier session left behind was not meant for this one
+    inbox.take().ok();
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            let control = match signal {
+                SIGTSTP => Control::Pause,
+                SIGCONT => Control::Resume,
+                SIGUSR1 => Control::Skip,
+                SIGUSR2 => {
+                    for control in inbox.take().unwrap_or_default() {
+                        controls.send(control).ok();
+                    }
+                    continue;
+                }
+                _ if cancelled.swap(true, Ordering::SeqCst) => {
+                    tui::restore();
+                    title::restore();
+                    restore_terminal();
+                    process::exit(128 + signal);
+                }
+                _ => {
+                    CAUGHT.store(signal, Ordering::SeqCst);
+                    continue;
+                }
+            };
+            controls.send(control).ok();
+        }
+    });
+    handle
+}
+
+// Render the countdown line in place
+// \r (carriage return) moves cursor to start of line, overwriting previous output,
+// and \x1b[K clears whatever was left over from a longer previous line (e.g. "PAUSED")
+// `counts` is the interruption counter, empty until one is logged
+fn render_countdown(label: &str, time: &str, counts: &str, paused: bool) {
+    if paused {
+        print!(
+            "\r{label}: {time}{counts} ⏸  PAUSED (p/space resume, s skip, r restart, +/- 1 min, i/e interruption, v void, Ctrl+C cancel)\x1b[K"
+        );
+    } else {
+        print!(
+            "\r{label}: {time}{counts} (p/space pause, s skip, r restart, +/- 1 min, i/e interruption, v void, Ctrl+C cancel)\x1b[K"
+        );
+    }
+    io::stdout().flush().ok(); // Force output to display immediately (stdout is buffered)
+}
+
+// The terminal front-end: turns session events into the familiar line-based output
+// Holds the number of cycles so it can print "Session n/N" headers, and the
+// style the user picked for showing the remaining time
+// When stdout is not a terminal (`live` is false) the \r-overwritten countdown
+// would just pile up, so only phase changes and pauses are printed, one per line
+struct TextRenderer {
+    cycles: u64,
+    time_format: TimeFormat,
+    // The task the session is spent on, shown in the headers
+    task: Option<String>,
+    live: bool,
+    // Whether the last line printed for this phase was paused; None until one is
+    shown_paused: Option<bool>,
+    // Internal and external interruptions logged in this phase
+    interruptions: (u32, u32),
+}
+
+impl TextRenderer {
+    // The interruption counter for the countdown line, e.g. "  [i:1 e:2]"
+    fn counts(&self) -> String {
+        match self.interruptions {
+            (0, 0) => String::new(),
+            (internal, external) => format!("  [i:{internal} e:{external}]"),
+        }
+    }
+}
+
+impl Observer for TextRenderer {
+    fn on_event(&mut self, event: &Event) {
+        match *event {
+            Event::PhaseStarted { cycle, phase, .. } => {
+                self.shown_paused = None;
+                self.interruptions = (0, 0);
+                // Display current session progress at the start of each focus phase
+                if phase == Phase::Focus {
+                    match &self.task {
+                        Some(task) => println!("\n=== Session {cycle}/{}: {task} ===", self.cycles),
+                        None => println!("\n=== Session {cycle}/{} ===", self.cycles),
+                    }
+                }
+            }
+            Event::Tick {
+                phase,
+                remaining,
+                total,
+                paused,
+                ..
+            } => {
+                let time = format_time(remaining, total, self.time_format);
+                if self.live {
+                    render_countdown(phase.label(), &time, &self.counts(), paused)
+                } else if self.shown_paused != Some(paused) {
+                    // Phase start, pause or resume: worth a line of its own
+                    let state = if paused { " (paused)" } else { "" };
+                    println!("{}: {time} left{state}", phase.label());
+                    self.shown_paused = Some(paused);
+                }
+            }
+            Event::PhaseCompleted {
+                phase,
+                outcome,
+                ref reason,
+                ..
+            } => {
+                if self.live {
+                    println!(); // Move off the countdown line before printing the result
+                }
+                match (outcome, phase) {
+                    (Outcome::Completed, Phase::Focus) => println!("✅ Focus done"),
+                    (Outcome::Completed, _) => println!("☕ {} over", phase.label()),
+                    (Outcome::Skipped, _) => println!("⏭️  {} skipped", phase.label()),
+                    (Outcome::Restarted, _) => {
+                        println!("🔁 Restarting {}", phase.label().to_lowercase())
+                    }
+                    (Outcome::Voided, _) => match reason {
+                        Some(reason) => println!("🚫 Pomodoro voided ({reason}), starting over"),
+                        None => println!("🚫 Pomodoro voided, starting over"),
+                    },
+                    (Outcome::Cancelled, _) => {}
+                }
+            }
+            Event::Interrupted { kind, ref note, .. } => {
+                match kind {
+                    Interruption::Internal => self.interruptions.0 += 1,
+                    Interruption::External => self.interruptions.1 += 1,
+                }
+                // Live, the counter on the countdown line says it; otherwise log a line
+                if !self.live {
+                    let note = note.as_deref().map(|note| format!(": {note}"));
+                    println!("Interruption ({}){}", kind.name(), note.unwrap_or_default());
+                }
+            }
+            // Celebrate completion of all sessions
+            Event::SessionCompleted => println!("\n🎉 All sessions done. Nice work."),
+            Event::Cancelled { .. } => println!("\n⏹️  Timer cancelled"),
+        }
+    }
+}
+
+// Run a full Pomodoro session in the terminal
+fn run(args: RunArgs, display: DisplayArgs) {
+    let plan = args.plan();
+
+    // Display the configuration for this pomodoro session
+    // This helps users confirm they've set the right parameters
+    if let Some(profile) = &plan.profile {
+        display.say(&format!("Using profile '{profile}'"));
+    }
+    display.say(&format!(
+        "Run with focus={}, break-min={}, cycles={}",
+        format_duration(plan.config.focus_secs),
+        format_duration(plan.config.break_secs),
+        plan.config.cycles
+    ));
+    if let Some(task) = &plan.task {
+        display.say(&format!("Working on '{task}'"));
+    }
+    if !plan.labels.is_empty() {
+        display.say(&format!("Filed under {}", plan.labels));
+    }
+    run_session(plan, None, &display);
+}
+
+// The config file, for its [hooks] and [notifications] sections; problems with
+// the file were already reported when the settings were resolved, so here they
+// just mean the built-in defaults
+fn load_config_file() -> ConfigFile {
+    match paths::config_file().as_deref().map(ConfigFile::load) {
+        Some(Ok(Some(file))) => file,
+        _ => ConfigFile::default(),
+    }
+}
+
+// The observers every session gets, wherever it runs: each phase is recorded in
+// the history as soon as it ends, the live state is saved so `pomodoro resume`
+// can carry on if the process dies, the user's hooks run at each transition,
+// sounds mark the ends of phases and a desktop notification, whose buttons send
+// `controls`, says what comes next
+// `pomodoro run --reflect` also hands the history a way to ask the user how
+// each completed pomodoro went; the history comes last, so that every other
+// observer has had the end of the pomodoro while the question waits
+fn recorders(plan: &Plan, controls: &Sender<Control>, reflect: Option<Reflect>) -> Fanout {
+    let mut observers = Fanout::new();
+    let file = load_config_file();
+    if !file.hooks.is_empty() {
+        observers.push(HookRunner::new(
+            file.hooks,
+            plan.config.cycles,
+            plan.profile.clone(),
+        ));
+    }
+    match file.sounds.backend() {
+        Ok(backend) => {
+            let player = SoundPlayer::new(file.sounds, backend, plan.config.cycles);
+            // The rating question comes before the end of the session
+            observers.push(match reflect {
+                Some(_) => player.with_last_focus_end(),
+                None => player,
+            })
+        }
+        Err(err) => eprintln!("warning: no sounds: {err}"),
+    }
+    let enabled = file.notifications.enabled;
+    if enabled != Some(false) {
+        match Notifier::connect(file.notifications, plan.config.cycles, controls.clone()) {
+            Ok(notifier) => observers.push(notifier),
+            // Without an explicit `enabled = true`, no session bus just means no notifications
+            Err(err) if enabled == Some(true) => {
+                eprintln!("warning: desktop notifications are off: {err}")
+            }
+            Err(_) => {}
+        }
+    }
+    if let Some(path) = paths::state_file() {
+        observers.push(
+            StateSaver::new(
+                StateFile::new(path),
+                plan.config.clone(),
+                plan.profile.clone(),
+                plan.time_format,
+            )
+            .with_task(plan.task.clone(), plan.task_id)
+            .with_labels(plan.labels.clone()),
+        );
+    }
+    match paths::history_file() {
+        Some(path) => {
+            let mut recorder = HistoryRecorder::new(HistoryStore::new(path), plan.profile.clone())
+                .with_task(plan.task.clone(), plan.task_id)
+                .with_labels(plan.labels.clone());
+            if let Some(reflect) = reflect {
+                recorder = recorder.with_reflection(reflect);
+            }
+            observers.push(recorder)
+        }
+        None => eprintln!("warning: $HOME is not set, this session will not be recorded"),
+    }
+    observers
+}
+
+// Asks the user how a completed pomodoro went
+type Reflect = Box<dyn FnMut(&PhaseRecord) -> Reflection + Send>;
+
+// Run a session in the terminal until it completes or is cancelled, starting
+// from `position` when resuming; keys control it and its events are rendered
+fn run_session(plan: Plan, position: Option<Position>, display: &DisplayArgs) {
+    display.say(
+        "Keys: p/space pause, s skip, r restart, +/- adjust by a minute, i/e log an interruption, v void the pomodoro",
+    );
+    display.say("Press Ctrl+C at any time to cancel the session");
+
+    // Keys pressed during the session are forwarded to it as controls
+    // The listener lives for the whole session and restores the terminal when dropped
+    let (tx, rx) = mpsc::channel();
+    let listener = KeyListener::start(tx.clone());
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let signals = handle_signals(Arc::clone(&cancelled), tx.clone());
+
+    // Run every focus/break phase, rendering events as they arrive
+    let mut observers = Fanout::new();
+    let tui = display.tui && io::stdout().is_terminal();
+    if display.tui && !tui {
+        eprintln!("warning: --tui needs a terminal, showing plain output instead");
+    }
+    // The reflection prompt is typed at the countdown line, so it needs the plain
+    // text display on a terminal
+    let mut reflect: Option<Reflect> = None;
+    if display.reflect {
+        match &listener {
+            Some(listener)
+                if display.output == Output::Text && !tui && io::stdout().is_terminal() =>
+            {
+                let lines = listener.lines.clone();
+                let idle = Duration::from_secs(display.reflect_timeout);
+                let cancelled = Arc::clone(&cancelled);
+                reflect = Some(Box::new(move |_: &PhaseRecord| {
+                    ask_reflection(&lines, idle, &cancelled)
+                }));
+            }
+            _ => eprintln!(
+                "warning: --reflect needs a terminal and the text display, not asking about pomodoros"
+            ),
+        }
+    }
+    match display.output {
+        Output::Text if tui => observers.push(TuiRenderer::start(
+            plan.config.clone(),
+            plan.time_format,
+            plan.task.clone(),
+            completed_today(),
+        )),
+        Output::Text => observers.push(TextRenderer {
+            cycles: plan.config.cycles,
+            time_format: plan.time_format,
+            task: plan.task.clone(),
+            live: io::stdout().is_terminal(),
+            shown_paused: None,
+            interruptions: (0, 0),
+        }),
+        Output::Json => observers.push(JsonRenderer::new(io::stdout())),
+    }
+    // The title and tab progress follow the countdown wherever the terminal shows them
+    if display.output == Output::Text && io::stdout().is_terminal() {
+        observers.push(TerminalTitle::stdout(plan.config.cycles, plan.time_format));
+    }
+    observers.push(recorders(&plan, &tx, reflect));
+
+    let mut session = Session::new(plan.config).with_cancel_flag(cancelled);
+    if let Some(position) = position {
+        session = session.starting_at(position);
+    }
+    let outcome = session.run(&rx, &mut observers);
+
+    // The full-screen display is gone once its observer is dropped, so say how
+    // the session ended on the normal screen
+    drop(observers);
+    drop(listener);
+    if tui {
+        match outcome {
+            Outcome::Completed => println!("🎉 All sessions done. Nice work."),
+            _ => println!("⏹️  Timer cancelled"),
+        }
+    }
+
+    signals.close();
+    let signal = CAUGHT.load(Ordering::SeqCst);
+    if signal != 0 {
+        process::exit(128 + signal);
+    }
+}
+
+// The number of pomodoros completed so far today, for the TUI's counter
+fn completed_today() -> u64 {
+    paths::history_file()
+        .and_then(|path| HistoryStore::new(path).load().ok())
+        .map_or(0, |records| {
+            stats::report(&records, DateRange::day(stats::today()), &Labels::default()).completed
+        })
+}
+
+// `pomodoro resume`: resume the daemon's paused session if a daemon is running,
+// otherwise carry on in the foreground with the session saved in the state file.
+// Time that passed while no process was running counts as if the timer had kept
+// going, unless the session was paused when it stopped
+fn resume(display: DisplayArgs) {
+    if let Ok(mut client) = Client::connect(&paths::socket_file()) {
+        let response = client.send(&Request::Resume).unwrap_or_else(|err| {
+            fail(
+                &format!("no answer from the daemon: {err}"),
+                EXIT_UNAVAILABLE,
+            )
+        });
+        match response.error {
+            Some(error) => fail(&error, 1),
+            None => println!("Resumed"),
+        }
+        return;
+    }
+
+    let Some(path) = paths::state_file() else {
+        fail(&"cannot locate the state file: $HOME is not set", 1);
+    };
+    let file = StateFile::new(&path);
+    let state = match file.load() {
+        Ok(Some(state)) => state,
+        Ok(None) => fail(&"there is no interrupted session to resume", 1),
+        Err(err) => fail(&format!("cannot read {}: {err}", path.display()), 1),
+    };
+    if state.is_running() {
+        fail(
+            &format!("the session is still running (process {})", state.pid),
+            1,
+        );
+    }
+
+    let now = Local::now();
+    let away = (now - state.saved_at).num_seconds().max(0) as u64;
+    let Some(position) = state.position_at(now) else {
+        display.say(&format!(
+            "The interrupted session would have finished during the {} since it stopped",
+            format_duration(away)
+        ));
+        file.remove().ok();
+        return;
+    };
+
+    if let Some(profile) = &state.profile {
+        display.say(&format!("Using profile '{profile}'"));
+    }
+    display.say(&format!(
+        "Resuming session {}/{}: {} with {} left",
+        position.cycle,
+        state.config.cycles,
+        position.phase.label().to_lowercase(),
+        format_duration(position.total.saturating_sub(position.elapsed))
+    ));
+    if state.paused || state.stopped {
+        display.say("(it was paused or cancelled, so the time since then does not count)");
+    } else if away > 0 {
+        display.say(&format!(
+            "({} passed while the timer was not running)",
+            format_duration(away)
+        ));
+    }
+
+    let plan = Plan {
+        config: state.config,
+        profile: state.profile,
+        time_format: state.time_format,
+        task: state.task,
+        task_id: state.task_id,
+        labels: state.labels,
+    };
+    run_session(plan, Some(position), &display);
+}
+
+// `pomodoro daemon`: run sessions in the background for the client commands
+// With --detach the daemon is started as a new process in its own session, so
+// closing the terminal (and the SIGHUP that comes with it) does not touch it
+fn daemon(args: DaemonArgs) {
+    let socket = paths::socket_file();
+    if args.detach {
+        detach_daemon(&socket);
+        return;
+    }
+
+    let daemon = Daemon::bind(&socket).unwrap_or_else(|err| fail(&err, 1));
+    let mut daemon =
+        daemon.with_observers(Box::new(|plan, controls| recorders(plan, controls, None)));
+    if let Some(path) = paths::state_file() {
+        daemon = daemon.with_state_file(StateFile::new(path));
+    }
+    eprintln!("pomodoro daemon listening on {}", daemon.socket().display());
+
+    // A stop signal cancels the running session, so it is recorded like a
+    // `pomodoro stop`, before the daemon exits
+    let shutdown = daemon.shutdown_handle();
+    let mut signals = Signals::new(STOP_SIGNALS)
+        .unwrap_or_else(|err| fail(&format!("cannot handle signals: {err}"), 1));
+    thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            shutdown.shutdown();
+            process::exit(128 + signal);
+        }
+    });
+
+    if let Err(err) = daemon.serve() {
+        fail(&format!("{}: {err}", daemon.socket().display()), 1);
+    }
+}
+
+// Start `pomodoro daemon` in the background and wait until it answers
+// The child is deliberately not waited for: it outlives us and is reparented to init
+#[allow(clippy::zombie_processes)]
+fn detach_daemon(socket: &Path) {
+    if Client::connect(socket).is_ok() {
+        fail(
+            &format!("a daemon is already listening on {}", socket.display()),
+            1,
+        );
+    }
+    let exe = env::current_exe()
+        .unwrap_or_else(|err| fail(&format!("cannot find our own executable: {err}"), 1));
+    let mut command = process::Command::new(exe);
+    command
+        .arg("daemon")
+        .stdin(process::Stdio::null())
+        .stdout(process::Stdio::null())
+        .stderr(process::Stdio::null());
+    // SAFETY: setsid is async-signal-safe, as required between fork and exec
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+    let mut child = command
+        .spawn()
+        .unwrap_or_else(|err| fail(&format!("cannot start the daemon: {err}"), 1));
+
+    // Give it a couple of seconds to take the lock and open the socket
+    for _ in 0..40 {
+        if Client::connect(socket).is_ok() {
+            println!("Daemon started (pid {})", child.id());
+            return;
+        }
+        if let Ok(Some(status)) = child.try_wait() {
+            fail(&format!("the daemon exited with {status}"), 1);
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    fail(
+        &format!("the daemon did not open {} in time", socket.display()),
+        EXIT_UNAVAILABLE,
+    );
+}
+
+// Send one request to the daemon; an unreachable daemon or an error answer ends
+// the process, so callers only ever see successful responses
+fn ask_daemon(request: &Request) -> Response {
+    let socket = paths::socket_file();
+    let response = Client::connect(&socket)
+        .and_then(|mut client| client.send(request))
+        .unwrap_or_else(|err| {
+            fail(
+                &format!(
+                    "cannot reach the daemon at {}: {err} (start it with `pomodoro daemon --detach`)",
+                    socket.display()
+                ),
+                EXIT_UNAVAILABLE,
+            )
+        });
+    match response.error {
+        Some(error) => fail(&error, 1),
+        None => response,
+    }
+}
+
+// `pomodoro interrupt`: log an interruption with the session that is running
+fn interrupt(args: InterruptArgs) {
+    let kind = if args.external {
+        Interruption::External
+    } else {
+        Interruption::Internal
+    };
+    let note = args.note.filter(|note| !note.trim().is_empty());
+    send_to_focus(
+        Request::Interrupt {
+            kind,
+            note: note.clone(),
+        },
+        Control::Interrupt(kind, note),
+        "interruptions are only logged during focus phases",
+    );
+    println!("Logged an {} interruption", kind.name());
+}
+
+// `pomodoro void`: void the running focus phase so it starts over
+fn void(reason: Option<String>) {
+    let reason = reason.filter(|reason| !reason.trim().is_empty());
+    send_to_focus(
+        Request::Void {
+            reason: reason.clone(),
+        },
+        Control::Void(reason),
+        "only focus phases can be voided",
+    );
+    println!("Voided the pomodoro; it starts again");
+}
+
+// Hand a control meant for a focus phase to the session that is running, failing
+// with `not_focus` during a break. The daemon takes `request` over its socket; a
+// foreground session finds `control` in the inbox when its process gets SIGUSR2
+fn send_to_focus(request: Request, control: Control, not_focus: &str) {
+    match current_status() {
+        None => fail(&"no session is running", 1),
+        Some((status, _)) if status.phase.is_break() => fail(&not_focus, 1),
+        Some((_, "daemon")) => {
+            ask_daemon(&request);
+        }
+        Some(_) => {
+            // current_status only reports a foreground session whose process is alive
+            let pid = paths::state_file()
+                .and_then(|path| StateFile::new(path).load().ok().flatten())
+                .map_or(0, |state| state.pid);
+            let inbox = Inbox::new(paths::interrupts_file());
+            if let Err(err) = inbox.push(&control) {
+                fail(
+                    &format!("cannot write {}: {err}", inbox.path().display()),
+                    1,
+                );
+            }
+            // SAFETY: kill only sends a signal
+            if pid == 0 || unsafe { libc::kill(pid as libc::pid_t, SIGUSR2) } != 0 {
+                fail(&"cannot reach the running session", 1);
+            }
+        }
+    }
+}
+
+// The running session and what runs it: the daemon if it has one, otherwise a
+// foreground `pomodoro run` that is still alive, going by the state file it keeps
+fn current_status() -> Option<(Status, &'static str)> {
+    if let Ok(mut client) = Client::connect(&paths::socket_file())
+        && let Ok(Response {
+            session: Some(status),
+            ..
+        }) = client.send(&Request::Status)
+    {
+        return Some((status, "daemon"));
+    }
+    let state = StateFile::new(paths::state_file()?).load().ok()??;
+    if !state.is_running() {
+        return None;
+    }
+    Some((state.status_at(Local::now())?, "foreground"))
+}
+
+// One line of `pomodoro status` output for the current session, or for no session
+fn status_line(args: &StatusArgs, time_format: TimeFormat) -> String {
+    let current = current_status();
+    if args.json {
+        let json = match current {
+            Some((status, source)) => serde_json::json!({
+                "active": true,
+                "source": source,
+                "phase": status.phase,
+                "label": status.phase.label(),
+                "cycle": status.cycle,
+                "cycles": status.cycles,
+                "remaining": status.remaining,
+                "remaining_text": format_time(status.remaining, status.total, time_format),
+                "total": status.total,
+                "paused": status.paused,
+            }),
+            None => serde_json::json!({ "active": false }),
+        };
+        return json.to_string();
+    }
+    match (current, &args.format) {
+        (Some((status, _)), Some(template)) => template.render(&status, time_format),
+        // Status bars hide an empty line, which suits an idle timer
+        (None, Some(_)) => String::new(),
+        (Some((status, _)), None) => format!(
+            "{} {}/{}: {}{}",
+            status.phase.label(),
+            status.cycle,
+            status.cycles,
+            format_time(status.remaining, status.total, time_format),
+            if status.paused { " (paused)" } else { "" }
+        ),
+        (None, None) => "No session running".to_string(),
+    }
+}
+
+// `pomodoro status`: where the running session is, once or every second
+fn status(args: StatusArgs) {
+    let time_format = args.time_format.unwrap_or_default();
+    let mut stdout = io::stdout();
     loop {
-        // Check for cancellation request before each iteration
-        // This ensures responsive cancellation even during long countdowns
-        if cancelled.load(Ordering::SeqCst) {
-            println!("\n⏹️  Timer cancelled"); // Inform user that timer was cancelled
-            return false; // Return false to indicate cancellation occurred
+        let line = status_line(&args, time_format);
+        // The reader went away (status bar restarted, `| head`): nothing left to do
+        if writeln!(stdout, "{line}")
+            .and_then(|()| stdout.flush())
+            .is_err()
+        {
+            return;
+        }
+        if !args.watch {
+            return;
         }
+        thread::sleep(Duration::from_secs(1));
+    }
+}
 
-        // Calculate how many seconds remain at this tick
-        // saturating_sub prevents underflow if tick somehow exceeds secs
-        let remaining = secs.saturating_sub(tick);
+// `pomodoro config show`: the fully resolved settings, as they would appear in the file
+fn config_show(args: RunArgs) {
+    let path = paths::config_file();
+    let settings = args.resolve().resolved();
+    match &path {
+        Some(path) if path.exists() => println!("# Config file: {}", path.display()),
+        Some(path) => println!("# No config file at {} (built-in defaults)", path.display()),
+        None => println!("# No config file location (built-in defaults)"),
+    }
+    if let Some(profile) = &args.profile {
+        println!("# Profile: {profile}");
+    }
+    // Serializing plain strings and integers cannot fail
+    print!("{}", toml::to_string(&settings).unwrap_or_default());
 
-        // Render the current countdown state
-        // \r (carriage return) moves cursor to start of line, overwriting previous output
-        // This creates the effect of a timer that updates in place rather than scrolling
-        print!("\r{label}: {} (Ctrl+C to cancel)", fmt_mm_ss(remaining));
-        io::stdout().flush().ok(); // Force output to display immediately (stdout is buffered)
+    if let Some(Ok(Some(file))) = path.as_deref().map(ConfigFile::load)
+        && !file.profiles.is_empty()
+    {
+        let names: Vec<&str> = file.profiles.keys().map(String::as_str).collect();
+        println!("# Available profiles: {}", names.join(", "));
+    }
+}
 
-        // Check if countdown is complete
-        if remaining == 0 {
-            println!(); // Add newline after finishing countdown to move to next line
-            return true; // Return true to indicate successful completion
+// `pomodoro config edit`: open the file in the user's editor, then check it
+fn config_edit() -> i32 {
+    let Some(path) = paths::config_file() else {
+        fail(
+            &"cannot locate the config file: $HOME is not set",
+            EXIT_CONFIG,
+        );
+    };
+
+    // Start from a commented template so every available key is discoverable
+    if !path.exists() {
+        let created = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&path, config::TEMPLATE));
+        if let Err(err) = created {
+            fail(
+                &format!("cannot create {}: {err}", path.display()),
+                EXIT_CONFIG,
+            );
+        }
+    }
+
+    // $EDITOR may carry its own arguments (e.g. "code --wait"), so let the shell split it
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let status = process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg("sh")
+        .arg(&path)
+        .status();
+    match status {
+        Ok(status) if status.success() => config_validate(),
+        Ok(status) => fail(&format!("{editor} exited with {status}"), EXIT_CONFIG),
+        Err(err) => fail(&format!("cannot start {editor}: {err}"), EXIT_CONFIG),
+    }
+}
+
+// `pomodoro config validate`: report every problem in the file, not just the first
+// Returns the exit code: 0 when the file is usable as-is
+fn config_validate() -> i32 {
+    let Some(path) = paths::config_file() else {
+        fail(
+            &"cannot locate the config file: $HOME is not set",
+            EXIT_CONFIG,
+        );
+    };
+    let file = match ConfigFile::load(&path) {
+        Ok(Some(file)) => file,
+        Ok(None) => {
+            println!(
+                "No config file at {}; built-in defaults apply",
+                path.display()
+            );
+            return 0;
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            return err.exit_code();
+        }
+    };
+
+    let mut problems = 0;
+    for key in file.unknown_keys() {
+        eprintln!("error: unknown key `{key}`");
+        problems += 1;
+    }
+
+    // Check [defaults] on its own and each profile as it would be used
+    let mut sections = vec![("defaults".to_string(), file.settings(None))];
+    for name in file.profiles.keys() {
+        sections.push((format!("profiles.{name}"), file.settings(Some(name))));
+    }
+    for (section, settings) in sections {
+        let Some(settings) = settings else { continue };
+        match settings.session_config().validate() {
+            Ok(warnings) => {
+                for warning in warnings {
+                    eprintln!("warning: [{section}] {warning}");
+                }
+            }
+            Err(err) => {
+                eprintln!("error: [{section}] {err}");
+                problems += 1;
+            }
         }
+    }
+
+    if problems == 0 {
+        println!("{} is valid", path.display());
+        0
+    } else {
+        eprintln!("{problems} problem(s) in {}", path.display());
+        EXIT_CONFIG
+    }
+}
+
+// `pomodoro stats`: summarise the history for the chosen period
+fn stats(args: StatsArgs) {
+    let today = stats::today();
+    let range = if let Some(range) = args.range {
+        range
+    } else if args.week {
+        DateRange::week(today)
+    } else if args.month {
+        DateRange::month(today)
+    } else {
+        DateRange::day(today)
+    };
 
-        // Schedule next tick exactly 1 second from start + current tick count
-        // This approach prevents cumulative timing drift that would occur with
-        // simple sleep(1 second) calls, which can accumulate small errors
-        tick += 1;
-        let target: Instant = start + Duration::from_secs(tick);
-        let now: Instant = Instant::now();
+    let Some(path) = paths::history_file() else {
+        fail(&"cannot locate the history file: $HOME is not set", 1);
+    };
+    let records = match HistoryStore::new(&path).load() {
+        Ok(records) => records,
+        Err(err) => fail(&format!("cannot read {}: {err}", path.display()), 1),
+    };
+
+    let filter = Labels::new(args.project, args.tags);
+    let mut report = stats::report(&records, range, &filter);
+    if let Some(by) = args.by {
+        report = report.with_groups(&records, by);
+    }
+    if args.json {
+        // The report only holds numbers, dates and strings, so this cannot fail
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).unwrap_or_default()
+        );
+    } else {
+        print!("{}", report.to_table());
+    }
+}
 
-        // Sleep until the target time, or skip if we're running late
-        // This handles cases where the system is under load or hibernates
-        if target > now {
-            thread::sleep(target - now); // Sleep for the remaining time until next tick
-        } else {
-            // We're late (system hiccup, sleep, etc.) — skip sleeping to catch up
-            // The next iteration will recalculate and try to get back on schedule
+// The task store, or the end of the process when there is nowhere to keep it
+fn task_store() -> TaskStore {
+    match paths::tasks_file() {
+        Some(path) => TaskStore::new(path),
+        None => fail(&"cannot locate the task file: $HOME is not set", 1),
+    }
+}
+
+// The task `--task` refers to, added first when it names a task that does not exist
+fn task_for(key: &str) -> Task {
+    let store = task_store();
+    let task = match store.get(key) {
+        Ok(task) => task,
+        Err(TaskError::NotFound(_)) => {
+            let task = store.add(key, None).unwrap_or_else(|err| fail(&err, 1));
+            eprintln!("Added task #{} '{}'", task.id, task.name);
+            task
         }
+        Err(err) => fail(&err, 1),
+    };
+    if task.is_done() {
+        eprintln!("warning: task '{}' is marked done", task.name);
+    }
+    task
+}
+
+// `pomodoro task`: add, list, finish and estimate tasks
+fn task(action: TaskAction) {
+    let store = task_store();
+    match action {
+        TaskAction::Add { name, estimate } => {
+            let task = store
+                .add(&name, estimate)
+                .unwrap_or_else(|err| fail(&err, 1));
+            println!("Added task #{} '{}'", task.id, task.name);
+        }
+        TaskAction::Done { task } => {
+            let task = store
+                .update(&task, |task| task.done_at = Some(Local::now()))
+                .unwrap_or_else(|err| fail(&err, 1));
+            println!("Task #{} '{}' is done", task.id, task.name);
+        }
+        TaskAction::Estimate { task, pomodoros } => {
+            let task = store
+                .update(&task, |task| task.estimate = Some(pomodoros))
+                .unwrap_or_else(|err| fail(&err, 1));
+            println!(
+                "Task #{} '{}' should take {pomodoros} pomodoro(s)",
+                task.id, task.name
+            );
+        }
+        TaskAction::List {
+            all,
+            json,
+            project,
+            tags,
+        } => task_list(&store, all, json, &Labels::new(project, tags)),
+    }
+}
+
+// The tasks with the pomodoros recorded for each, as a table or JSON; only the
+// pomodoros filed under `filter` count
+fn task_list(store: &TaskStore, all: bool, json: bool, filter: &Labels) {
+    let tasks = store.load().unwrap_or_else(|err| fail(&err, 1));
+    let records = match paths::history_file().map(|path| HistoryStore::new(path).load()) {
+        Some(Ok(records)) => records,
+        Some(Err(err)) => fail(&format!("cannot read the history: {err}"), 1),
+        None => Vec::new(),
+    };
+    let records: Vec<_> = records
+        .into_iter()
+        .filter(|record| record.labels.includes(filter))
+        .collect();
+    let summaries: Vec<_> = tasks::summaries(&tasks, &records)
+        .into_iter()
+        .filter(|task| all || !task.done)
+        .collect();
+
+    if json {
+        // Only numbers, strings and booleans, so this cannot fail
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&summaries).unwrap_or_default()
+        );
+        return;
+    }
+    if summaries.is_empty() {
+        println!("No tasks; add one with `pomodoro task add NAME`");
+        return;
+    }
+    println!("{:>4}  {:<40} Pomodoros", "#", "Task");
+    for task in summaries {
+        let count = match task.estimate {
+            Some(estimate) => format!("{}/{estimate}", task.actual),
+            None => task.actual.to_string(),
+        };
+        let done = if task.done { "  (done)" } else { "" };
+        println!("{:>4}  {:<40} {count}{done}", task.id, task.name);
     }
 }
 
 // Main entry point of the application
 // This function orchestrates the entire Pomodoro session based on user input with cancellation support
 fn main() {
-    // Setup signal handler for graceful cancellation
-    // This must be done early to ensure Ctrl+C works throughout the entire session
-    let cancelled = setup_signal_handler();
-
     // Parse command-line arguments using clap
     // This will automatically handle --help, --version, and argument validation
     let cli: Cli = Cli::parse();
 
-    // Handle the parsed command using pattern matching
-    // Currently only handles the Run command, but structure allows easy extension
+    // Dispatch to the handler for the chosen subcommand
     match cli.command {
-        Command::Run {
-            focus,
-            break_min,
-            cycles,
-            long_break,
-            long_every,
-        } => {
-            // Display the configuration for this pomodoro session
-            // This helps users confirm they've set the right parameters
-            println!("Run with focus={focus}m, break-min={break_min}m, cycles={cycles}");
-            println!("Press Ctrl+C at any time to cancel the session");
-
-            // Convert minutes to seconds for the countdown functions
-            // All our timing functions work in seconds for precision
-            let focus_secs = focus * 60;
-
-            // Run the specified number of pomodoro cycles
-            // Each cycle consists of a focus period followed by a break (except the last)
-            for n in 1..=cycles {
-                // Display current session progress to help user track their progress
-                println!("\n=== Session {n}/{cycles} ===");
-
-                // Focus period - the main work time
-                // This is when the user should focus on their task without distractions
-                // If countdown returns false, it means the user cancelled, so we exit
-                if !countdown_secs(focus_secs, "Focus", &cancelled) {
-                    return; // Exit main function if focus period was cancelled
-                }
-                println!("✅ Focus done"); // Celebrate completion of focus time
-
-                // Break period (skip break after the last session)
-                // No need for a break after the final session since work is complete
-                if n < cycles {
-                    // Determine if this should be a long break or short break
-                    // Long breaks occur every 'long_every' sessions for better rest
-                    let is_long = n % long_every == 0;
-
-                    // Calculate break duration based on break type
-                    let break_secs = if is_long {
-                        long_break * 60 // Convert long break minutes to seconds
-                    } else {
-                        break_min * 60 // Convert short break minutes to seconds
-                    };
-
-                    // Set appropriate label for the break type
-                    let label = if is_long { "Long break" } else { "Break" };
-
-                    // Run the break countdown with appropriate duration and label
-                    // If countdown returns false, it means the user cancelled, so we exit
-                    if !countdown_secs(break_secs, label, &cancelled) {
-                        return; // Exit main function if break period was cancelled
-                    }
-                    println!("☕ {label} over"); // Signal that break time is finished
+        Command::Run { args, display } => run(args, display),
+        Command::Resume { display } => resume(display),
+        Command::Daemon(args) => daemon(args),
+        Command::Start(args) => {
+            let plan = args.plan();
+            ask_daemon(&Request::Start(plan));
+            println!("Session started");
+        }
+        Command::Pause => {
+            ask_daemon(&Request::Pause);
+            println!("Paused");
+        }
+        Command::Skip => {
+            ask_daemon(&Request::Skip);
+            println!("Skipped");
+        }
+        Command::Stop => {
+            ask_daemon(&Request::Stop);
+            println!("Session stopped");
+        }
+        Command::Interrupt(args) => interrupt(args),
+        Command::Void { reason } => void(reason),
+        Command::Status(args) => status(args),
+        Command::Stats(args) => stats(args),
+        Command::Task { action } => task(action),
+        Command::Config { action } => {
+            let code = match action {
+                ConfigAction::Show(args) => {
+                    config_show(args);
+                    0
                 }
-            }
-
-            // Celebrate completion of all sessions
-            // This provides positive reinforcement for completing the full Pomodoro session
-            println!("\n🎉 All sessions done. Nice work.");
+                ConfigAction::Edit => config_edit(),
+                ConfigAction::Validate => config_validate(),
+            };
+            process::exit(code);
         }
     }
 }
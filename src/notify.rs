@@ -0,0 +1,331 @@
+//! Desktop notifications at phase changes, from the `[notifications]` section.
+//!
+//! Notifications go to `org.freedesktop.Notifications` on the D-Bus session bus.
+//! Each one replaces the last, so a long session leaves a single notification
+//! behind, and can carry actions ("Start break", "Skip") that are fed back to
+//! the session as [`Control`]s. Sending happens on a worker thread so a slow
+//! notification server never holds up the countdown.
+
+use crate::config::duration_setting;
+use crate::duration::format_duration;
+use crate::session::{Control, Event, Observer, Outcome, Phase};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+use zbus::blocking::{Connection, MessageIterator};
+use zbus::zvariant::Value;
+
+const DESTINATION: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+const INTERFACE: &str = "org.freedesktop.Notifications";
+
+/// Summary shown when a phase ends, unless the config says otherwise.
+pub const DEFAULT_SUMMARY: &str = "{ended} done";
+/// Body shown when a phase ends, unless the config says otherwise.
+pub const DEFAULT_BODY: &str = "{next} for {duration} (session {cycle}/{cycles})";
+
+/// How insistent the notification server should be, per the notification spec.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+/// The `[notifications]` section of the config file.
+///
+/// `summary` and `body` are templates: `{ended}` is the phase that just ended,
+/// `{next}` the one starting, `{duration}` its length and `{cycle}`/`{cycles}`
+/// the position in the session.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Notifications {
+    /// Unset means "if there is a session bus"; `true` also warns when there is not
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub urgency: Option<Urgency>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// Whether to offer the "Start" and "Skip" buttons
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actions: Option<bool>,
+    /// How long a notification stays up, in seconds; the server decides when unset
+    #[serde(
+        default,
+        with = "duration_setting",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expire: Option<u64>,
+    /// Keys that are not settings, kept so they can be reported
+    #[serde(flatten, skip_serializing)]
+    pub unknown: BTreeMap<String, toml::Value>,
+}
+
+/// Replace each `{name}` in `template` with its value; other braces are left as they are.
+pub fn fill(template: &str, values: &[(&str, String)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{name}}}"), value)
+        })
+}
+
+// One notification waiting to be sent: what it says and the buttons it offers
+struct Job {
+    summary: String,
+    body: String,
+    actions: Vec<(&'static str, String)>,
+}
+
+/// Observer that sends a desktop notification whenever a phase runs to its end.
+///
+/// Phases that end early (skipped, restarted, cancelled) were ended by the user,
+/// so they only close the notification that is up. The one at the end of the
+/// session counts the pomodoros completed; its wording is fixed, as the
+/// templates' placeholders describe a phase change.
+pub struct Notifier {
+    settings: Notifications,
+    cycles: u64,
+    // Focus phases that ran to their end
+    completed: u64,
+    // The phase that just completed, waiting for the next one to start
+    ended: Option<Phase>,
+    // Notifications for the sending thread to show; `None` closes the current one instead
+    jobs: Option<Sender<Option<Job>>>,
+    connection: Option<Connection>,
+    sender: Option<JoinHandle<()>>,
+    listener: Option<JoinHandle<()>>,
+}
+
+impl Notifier {
+    /// Connect to the session bus named by `DBUS_SESSION_BUS_ADDRESS`.
+    pub fn connect(
+        settings: Notifications,
+        cycles: u64,
+        controls: Sender<Control>,
+    ) -> zbus::Result<Self> {
+        Ok(Notifier::new(
+            Connection::session()?,
+            settings,
+            cycles,
+            controls,
+        ))
+    }
+
+    /// Send notifications over `connection`; clicked actions go to `controls`.
+    pub fn new(
+        connection: Connection,
+        settings: Notifications,
+        cycles: u64,
+        controls: Sender<Control>,
+    ) -> Self {
+        // The id of the notification on screen, 0 when there is none
+        let current = Arc::new(AtomicU32::new(0));
+        let listener = {
+            let connection = connection.clone();
+            let current = Arc::clone(&current);
+            thread::spawn(move || listen(&connection, &current, &controls))
+        };
+
+        let (jobs, queue) = mpsc::channel::<Option<Job>>();
+        let sender = {
+            let connection = connection.clone();
+            let settings = settings.clone();
+            thread::spawn(move || {
+                let mut warned = false;
+                for job in queue {
+                    let result = match job {
+                        Some(job) => notify(&connection, &settings, &current, &job),
+                        None => close(&connection, &current),
+                    };
+                    if let Err(err) = result
+                        && !warned
+                    {
+                        eprintln!("\nwarning: cannot show a desktop notification: {err}");
+                        warned = true;
+                    }
+                }
+            })
+        };
+
+        Notifier {
+            settings,
+            cycles,
+            completed: 0,
+            ended: None,
+            jobs: Some(jobs),
+            connection: Some(connection),
+            sender: Some(sender),
+            listener: Some(listener),
+        }
+    }
+
+    fn send(&self, job: Option<Job>) {
+        if let Some(jobs) = &self.jobs {
+            jobs.send(job).ok();
+        }
+    }
+}
+
+// Show `job`, replacing the notification that is up
+fn notify(
+    connection: &Connection,
+    settings: &Notifications,
+    current: &AtomicU32,
+    job: &Job,
+) -> zbus::Result<()> {
+    let actions: Vec<&str> = job
+        .actions
+        .iter()
+        .flat_map(|(key, label)| [*key, label.as_str()])
+        .collect();
+    let urgency = match settings.urgency.unwrap_or_default() {
+        Urgency::Low => 0u8,
+        Urgency::Normal => 1,
+        Urgency::Critical => 2,
+    };
+    let hints = HashMap::from([("urgency", Value::U8(urgency))]);
+    let expire = settings.expire.map_or(-1, |secs| {
+        i32::try_from(secs.saturating_mul(1000)).unwrap_or(i32::MAX)
+    });
+
+    let reply = connection.call_method(
+        Some(DESTINATION),
+        PATH,
+        Some(INTERFACE),
+        "Notify",
+        &(
+            "pomodoro",
+            current.load(Ordering::SeqCst),
+            "",
+            job.summary.as_str(),
+            job.body.as_str(),
+            actions,
+            hints,
+            expire,
+        ),
+    )?;
+    current.store(reply.body().deserialize::<u32>()?, Ordering::SeqCst);
+    Ok(())
+}
+
+// Take down the notification that is up, if any
+fn close(connection: &Connection, current: &AtomicU32) -> zbus::Result<()> {
+    let id = current.swap(0, Ordering::SeqCst);
+    if id != 0 {
+        connection.call_method(
+            Some(DESTINATION),
+            PATH,
+            Some(INTERFACE),
+            "CloseNotification",
+            &id,
+        )?;
+    }
+    Ok(())
+}
+
+// Turn clicks on the current notification's actions into controls, until the
+// connection closes or the session stops listening
+fn listen(connection: &Connection, current: &AtomicU32, controls: &Sender<Control>) {
+    let rule = format!("type='signal',interface='{INTERFACE}',member='ActionInvoked'");
+    let Ok(signals) = MessageIterator::for_match_rule(rule.as_str(), connection, None) else {
+        return;
+    };
+    for message in signals {
+        let Ok(message) = message else { break };
+        let Ok((id, key)) = message.body().deserialize::<(u32, String)>() else {
+            continue;
+        };
+        if id == 0 || id != current.load(Ordering::SeqCst) {
+            continue;
+        }
+        let control = match key.as_str() {
+            // Start the phase over from now, for when the notification was seen late
+            "start" => Control::Restart,
+            "skip" => Control::Skip,
+            _ => continue,
+        };
+        if controls.send(control).is_err() {
+            break;
+        }
+    }
+}
+
+impl Observer for Notifier {
+    fn on_event(&mut self, event: &Event) {
+        match *event {
+            Event::PhaseCompleted { phase, outcome, .. } => {
+                if (phase, outcome) == (Phase::Focus, Outcome::Completed) {
+                    self.completed += 1;
+                }
+                self.ended = (outcome == Outcome::Completed).then_some(phase);
+            }
+            Event::PhaseStarted {
+                cycle,
+                phase,
+                duration,
+                ..
+            } => {
+                let Some(ended) = self.ended.take() else {
+                    self.send(None);
+                    return;
+                };
+                let values = [
+                    ("ended", ended.label().to_string()),
+                    ("next", phase.label().to_string()),
+                    ("duration", format_duration(duration)),
+                    ("cycle", cycle.to_string()),
+                    ("cycles", self.cycles.to_string()),
+                ];
+                let summary = self.settings.summary.as_deref().unwrap_or(DEFAULT_SUMMARY);
+                let body = self.settings.body.as_deref().unwrap_or(DEFAULT_BODY);
+                let actions = if self.settings.actions.unwrap_or(true) {
+                    let start = format!("Start {}", phase.label().to_lowercase());
+                    vec![("start", start), ("skip", "Skip".to_string())]
+                } else {
+                    Vec::new()
+                };
+                self.send(Some(Job {
+                    summary: fill(summary, &values),
+                    body: fill(body, &values),
+                    actions,
+                }));
+            }
+            Event::SessionCompleted => self.send(Some(Job {
+                summary: "All sessions done".to_string(),
+                body: format!(
+                    "{} of {} pomodoros completed. Nice work.",
+                    self.completed, self.cycles
+                ),
+                actions: Vec::new(),
+            })),
+            Event::Cancelled { .. } => self.send(None),
+            Event::Tick { .. } | Event::Interrupted { .. } => {}
+        }
+    }
+}
+
+impl Drop for Notifier {
+    fn drop(&mut self) {
+        // Let the sender finish what is queued, then close the connection so
+        // the listener's signal stream ends too
+        self.jobs.take();
+        if let Some(sender) = self.sender.take() {
+            sender.join().ok();
+        }
+        if let Some(connection) = self.connection.take() {
+            connection.close().ok();
+        }
+        if let Some(listener) = self.listener.take() {
+            listener.join().ok();
+        }
+    }
+}
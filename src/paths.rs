@@ -0,0 +1,77 @@
+//! Where pomodoro keeps its files, following the XDG base directory spec.
+
+use std::env;
+use std::path::PathBuf;
+
+/// Name of the directory created under each XDG base directory.
+const APP_DIR: &str = "pomodoro";
+
+// $XDG_<kind>_HOME, or the spec's fallback under $HOME when it is unset or empty
+fn xdg_home(var: &str, fallback: &str) -> Option<PathBuf> {
+    match env::var_os(var) {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)),
+    }
+}
+
+/// `~/.config/pomodoro`
+pub fn config_dir() -> Option<PathBuf> {
+    xdg_home("XDG_CONFIG_HOME", ".config").map(|dir| dir.join(APP_DIR))
+}
+
+/// The config file: `$POMODORO_CONFIG` if set, else `~/.config/pomodoro/config.toml`.
+pub fn config_file() -> Option<PathBuf> {
+    match env::var_os("POMODORO_CONFIG") {
+        Some(path) if !path.is_empty() => Some(PathBuf::from(path)),
+        _ => config_dir().map(|dir| dir.join("config.toml")),
+    }
+}
+
+/// `~/.local/share/pomodoro`, where the session history is kept.
+pub fn data_dir() -> Option<PathBuf> {
+    xdg_home("XDG_DATA_HOME", ".local/share").map(|dir| dir.join(APP_DIR))
+}
+
+/// The append-only log of every recorded phase.
+pub fn history_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("history.jsonl"))
+}
+
+/// The tasks pomodoros can be attributed to.
+pub fn tasks_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("tasks.json"))
+}
+
+/// `~/.local/state/pomodoro`, for state that only matters to the running session.
+pub fn state_dir() -> Option<PathBuf> {
+    xdg_home("XDG_STATE_HOME", ".local/state").map(|dir| dir.join(APP_DIR))
+}
+
+/// Snapshot of the running session, used by `pomodoro resume`.
+pub fn state_file() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("session.json"))
+}
+
+/// Interruptions waiting for the session running in the foreground.
+pub fn interrupts_file() -> PathBuf {
+    runtime_dir().join("interrupts.jsonl")
+}
+
+/// `$XDG_RUNTIME_DIR/pomodoro`, or a per-user directory under the system
+/// temporary directory when there is no runtime directory.
+pub fn runtime_dir() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir).join(APP_DIR),
+        // SAFETY: getuid cannot fail
+        _ => env::temp_dir().join(format!("{APP_DIR}-{}", unsafe { libc::getuid() })),
+    }
+}
+
+/// The daemon's control socket: `$POMODORO_SOCKET` if set, else `daemon.sock`
+/// in the runtime directory.
+pub fn socket_file() -> PathBuf {
+    match env::var_os("POMODORO_SOCKET") {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => runtime_dir().join("daemon.sock"),
+    }
+}
@@ -0,0 +1,59 @@
+//! Renderers that turn session events into output for other programs.
+
+use crate::session::{Event, Observer};
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::io::Write;
+
+// One line of the event stream: the event itself plus when it happened
+#[derive(Serialize)]
+struct Line<'a> {
+    time: DateTime<Local>,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+/// Observer that writes every event as one line of JSON (newline-delimited JSON).
+///
+/// Each object has an `event` name (`phase_start`, `tick`, `phase_end`,
+/// `interrupted`, `session_end` or `cancelled`), a `time` stamp and the event's fields:
+///
+/// ```text
+/// {"time":"2026-10-17T09:00:00+02:00","event":"phase_start","cycle":1,"phase":"focus","duration":1500}
+/// {"time":"2026-10-17T09:00:00+02:00","event":"tick","cycle":1,"phase":"focus","remaining":1500,"total":1500,"paused":false}
+/// ```
+///
+/// Output stops quietly once the reader goes away, e.g. at the end of `| head`.
+pub struct JsonRenderer<W: Write> {
+    out: W,
+    closed: bool,
+}
+
+impl<W: Write> JsonRenderer<W> {
+    pub fn new(out: W) -> Self {
+        JsonRenderer { out, closed: false }
+    }
+}
+
+impl<W: Write> Observer for JsonRenderer<W> {
+    fn on_event(&mut self, event: &Event) {
+        if self.closed {
+            return;
+        }
+        let line = Line {
+            time: Local::now(),
+            event,
+        };
+        // Events only hold numbers, names and booleans, so serializing cannot fail
+        let mut text = serde_json::to_string(&line).unwrap_or_default();
+        text.push('\n');
+        if self
+            .out
+            .write_all(text.as_bytes())
+            .and_then(|()| self.out.flush())
+            .is_err()
+        {
+            self.closed = true;
+        }
+    }
+}
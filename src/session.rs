@@ -0,0 +1,536 @@
+//! The focus/break scheduler and countdown loop.
+//!
+//! A [`Session`] walks through `cycles` focus phases, with a short or long break
+//! between each pair, and reports everything that happens to an [`Observer`] as
+//! [`Event`]s. It never prints anything itself: rendering is up to the consumer.
+
+use crate::clock::{Clock, SystemClock};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, TryRecvError};
+use std::time::{Duration, Instant};
+
+/// How often to wake up while paused to re-check the cancellation flag.
+const PAUSED_POLL: Duration = Duration::from_millis(250);
+
+/// How often to check for new controls while waiting for the next tick.
+const CONTROL_POLL: Duration = Duration::from_millis(50);
+
+/// The kind of phase a session is currently in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Work time
+    Focus,
+    /// A short break between two focus phases
+    Break,
+    /// The longer break taken every `long_every` focus phases
+    LongBreak,
+}
+
+impl Phase {
+    /// Human-readable name used in countdown lines and messages.
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::Focus => "Focus",
+            Phase::Break => "Break",
+            Phase::LongBreak => "Long break",
+        }
+    }
+
+    /// Whether this phase is one of the two kinds of break.
+    pub fn is_break(self) -> bool {
+        !matches!(self, Phase::Focus)
+    }
+}
+
+/// Durations (in seconds) and counts that make up a session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionConfig {
+    /// Length of each focus phase
+    pub focus_secs: u64,
+    /// Length of a short break
+    pub break_secs: u64,
+    /// Length of a long break
+    pub long_break_secs: u64,
+    /// Number of focus phases in the session
+    pub cycles: u64,
+    /// Take a long break after every N-th focus phase
+    pub long_every: u64,
+}
+
+impl Default for SessionConfig {
+    /// The traditional Pomodoro schedule: 25 minutes of focus, 5 minute breaks,
+    /// four cycles and a 15 minute long break every fourth focus phase.
+    fn default() -> Self {
+        SessionConfig {
+            focus_secs: 25 * 60,
+            break_secs: 5 * 60,
+            long_break_secs: 15 * 60,
+            cycles: 4,
+            long_every: 4,
+        }
+    }
+}
+
+impl SessionConfig {
+    /// Planned length of a phase, before any user adjustment.
+    pub fn duration_of(&self, phase: Phase) -> u64 {
+        match phase {
+            Phase::Focus => self.focus_secs,
+            Phase::Break => self.break_secs,
+            Phase::LongBreak => self.long_break_secs,
+        }
+    }
+
+    /// The break that follows focus phase `cycle`, or None after the last one.
+    pub fn break_after(&self, cycle: u64) -> Option<Phase> {
+        if cycle >= self.cycles {
+            None // No need for a break after the final focus phase
+        } else if cycle.is_multiple_of(self.long_every) {
+            Some(Phase::LongBreak)
+        } else {
+            Some(Phase::Break)
+        }
+    }
+
+    /// The phase that follows `phase` of focus phase `cycle`, as `(cycle, phase)`,
+    /// or None once the session is over.
+    pub fn next_phase(&self, cycle: u64, phase: Phase) -> Option<(u64, Phase)> {
+        match phase {
+            Phase::Focus => self.break_after(cycle).map(|next| (cycle, next)),
+            Phase::Break | Phase::LongBreak => Some((cycle + 1, Phase::Focus)),
+        }
+    }
+}
+
+/// A point part-way through a session, such as where an earlier run stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    /// The focus phase number (1-based)
+    pub cycle: u64,
+    pub phase: Phase,
+    /// Seconds of the phase already counted, not including pauses
+    pub elapsed: u64,
+    /// Length of the phase, after any extending or shortening
+    pub total: u64,
+    /// Seconds of `elapsed` that the run which was cancelled here already
+    /// reported in its [`Event::Cancelled`]; worked out when resuming, not saved
+    #[serde(skip)]
+    pub reported: u64,
+}
+
+/// Where an interruption of a focus phase came from; the technique marks them
+/// `'` and `-` on paper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interruption {
+    /// A thought or urge of one's own
+    Internal,
+    /// Someone or something else, such as a colleague or a chat message
+    External,
+}
+
+impl Interruption {
+    pub fn name(self) -> &'static str {
+        match self {
+            Interruption::Internal => "internal",
+            Interruption::External => "external",
+        }
+    }
+}
+
+/// Commands that can be sent to a running session, e.g. from a key listener.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Control {
+    /// Pause the countdown if it is running, resume it if it is paused
+    TogglePause,
+    /// Pause the countdown; does nothing if it is already paused
+    Pause,
+    /// Resume a paused countdown; does nothing if it is running
+    Resume,
+    /// End the current phase now and move on to the next one
+    Skip,
+    /// Start the current phase again from its full duration
+    Restart,
+    /// Add one minute to the current phase
+    Extend,
+    /// Remove one minute from the current phase
+    Shorten,
+    /// Log an interruption of the current focus phase, with an optional note;
+    /// ignored during breaks
+    Interrupt(Interruption, Option<String>),
+    /// Void the current focus phase, with an optional reason, and run it again
+    /// from the start; ignored during breaks
+    Void(Option<String>),
+}
+
+/// How a single phase ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// The countdown reached zero
+    Completed,
+    /// The user skipped the rest of the phase
+    Skipped,
+    /// The user asked to run the phase again from the start
+    Restarted,
+    /// The session was cancelled
+    Cancelled,
+    /// The focus phase was interrupted for good and does not count; it runs again
+    Voided,
+}
+
+/// Everything a session reports to its observer.
+///
+/// Serializes as a JSON object tagged with an `event` name, e.g.
+/// `{"event":"tick","cycle":1,"phase":"focus","remaining":1499,"total":1500,"paused":false}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// A phase began with the given planned duration; when a session that was
+    /// cancelled part-way through it is resumed, `reported` is the seconds of it
+    /// the cancelled run already reported
+    #[serde(rename = "phase_start")]
+    PhaseStarted {
+        cycle: u64,
+        phase: Phase,
+        duration: u64,
+        #[serde(skip_serializing_if = "is_zero")]
+        reported: u64,
+    },
+    /// Once per second while running, and right after every control that changes
+    /// what should be displayed (pause, resume, extend, shorten)
+    Tick {
+        cycle: u64,
+        phase: Phase,
+        remaining: u64,
+        total: u64,
+        paused: bool,
+    },
+    /// A phase ended without cancelling the session; `elapsed` excludes paused time
+    /// and `reason` is what the user gave for voiding it
+    #[serde(rename = "phase_end")]
+    PhaseCompleted {
+        cycle: u64,
+        phase: Phase,
+        outcome: Outcome,
+        elapsed: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+    /// The user logged an interruption of a focus phase; the countdown goes on
+    Interrupted {
+        cycle: u64,
+        phase: Phase,
+        kind: Interruption,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+    },
+    /// The last focus phase is over
+    #[serde(rename = "session_end")]
+    SessionCompleted,
+    /// The session was cancelled part-way through a phase
+    Cancelled {
+        cycle: u64,
+        phase: Phase,
+        elapsed: u64,
+    },
+}
+
+// For leaving fields that are usually zero out of the serialized events
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+/// Receives the events of a running session.
+pub trait Observer {
+    fn on_event(&mut self, event: &Event);
+}
+
+// Plain closures make handy observers for small consumers and tests
+impl<F: FnMut(&Event)> Observer for F {
+    fn on_event(&mut self, event: &Event) {
+        self(event)
+    }
+}
+
+/// Several observers behind one, each seeing every event in the order they were added.
+#[derive(Default)]
+pub struct Fanout {
+    observers: Vec<Box<dyn Observer + Send>>,
+}
+
+impl Fanout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, observer: impl Observer + Send + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+}
+
+impl Observer for Fanout {
+    fn on_event(&mut self, event: &Event) {
+        for observer in &mut self.observers {
+            observer.on_event(event);
+        }
+    }
+}
+
+/// A Pomodoro session: the schedule of phases plus the position within it.
+pub struct Session {
+    config: SessionConfig,
+    clock: Box<dyn Clock + Send>,
+    cancelled: Arc<AtomicBool>,
+    cycle: u64,
+    phase: Phase,
+    // Where to start in the first phase when starting part-way
+    resume: Option<Position>,
+}
+
+impl Session {
+    /// Create a session positioned at the first focus phase.
+    pub fn new(config: SessionConfig) -> Self {
+        Session {
+            config,
+            clock: Box::new(SystemClock),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            cycle: 1,
+            phase: Phase::Focus,
+            resume: None,
+        }
+    }
+
+    /// Start from `position` instead of the beginning of the first focus phase.
+    pub fn starting_at(mut self, position: Position) -> Self {
+        self.cycle = position.cycle;
+        self.phase = position.phase;
+        self.resume = Some(position);
+        self
+    }
+
+    /// Use `clock` instead of the system clock for all timing.
+    pub fn with_clock(mut self, clock: impl Clock + Send + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Share a cancellation flag with the session; setting it ends the run.
+    pub fn with_cancel_flag(mut self, cancelled: Arc<AtomicBool>) -> Self {
+        self.cancelled = cancelled;
+        self
+    }
+
+    pub fn config(&self) -> &SessionConfig {
+        &self.config
+    }
+
+    /// The focus phase number (1-based) the session is in or is about to start.
+    pub fn cycle(&self) -> u64 {
+        self.cycle
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// Run the session from its current position to the end.
+    ///
+    /// Returns `Outcome::Completed` when every phase has run, or
+    /// `Outcome::Cancelled` if the session was cancelled on the way.
+    pub fn run(&mut self, controls: &Receiver<Control>, observer: &mut dyn Observer) -> Outcome {
+        loop {
+            let (cycle, phase) = (self.cycle, self.phase);
+            // Only the first phase of a resumed session starts part-way through
+            let Position {
+                elapsed: done,
+                total: duration,
+                reported,
+                ..
+            } = self.resume.take().unwrap_or(Position {
+                cycle,
+                phase,
+                elapsed: 0,
+                total: self.config.duration_of(phase),
+                reported: 0,
+            });
+            observer.on_event(&Event::PhaseStarted {
+                cycle,
+                phase,
+                duration,
+                reported,
+            });
+
+            let (outcome, elapsed, reason) = self.countdown(duration, done, controls, observer);
+            if outcome == Outcome::Cancelled {
+                observer.on_event(&Event::Cancelled {
+                    cycle,
+                    phase,
+                    elapsed,
+                });
+                return Outcome::Cancelled;
+            }
+            observer.on_event(&Event::PhaseCompleted {
+                cycle,
+                phase,
+                outcome,
+                elapsed,
+                reason,
+            });
+
+            // A restarted or voided phase runs again from the top; anything else moves on
+            let again = matches!(outcome, Outcome::Restarted | Outcome::Voided);
+            if !again && !self.advance() {
+                observer.on_event(&Event::SessionCompleted);
+                return Outcome::Completed;
+            }
+        }
+    }
+
+    // Move to the phase after the current one; false once the session is over
+    fn advance(&mut self) -> bool {
+        match self.config.next_phase(self.cycle, self.phase) {
+            Some((cycle, phase)) => {
+                self.cycle = cycle;
+                self.phase = phase;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Count down one phase, reacting to controls, and return how it ended
+    // together with the number of (unpaused) seconds that actually elapsed and,
+    // for a voided phase, the reason given
+    // `done` is how much of the phase was already counted before, when resuming
+    fn countdown(
+        &mut self,
+        secs: u64,
+        done: u64,
+        controls: &Receiver<Control>,
+        observer: &mut dyn Observer,
+    ) -> (Outcome, u64, Option<String>) {
+        let (cycle, phase) = (self.cycle, self.phase);
+        let mut total: u64 = secs; // Phase length, which the user may extend or shorten
+        let mut start: Instant = self.clock.now(); // The moment we started counting
+        let mut tick: u64 = done; // How many seconds of the phase have elapsed
+
+        let tick_event = |remaining: u64, total: u64, paused: bool| Event::Tick {
+            cycle,
+            phase,
+            remaining,
+            total,
+            paused,
+        };
+
+        loop {
+            if self.cancelled.load(Ordering::SeqCst) {
+                return (Outcome::Cancelled, tick, None);
+            }
+
+            // saturating_sub prevents underflow if the phase was shortened below `tick`
+            let remaining = total.saturating_sub(tick);
+            observer.on_event(&tick_event(remaining, total, false));
+            if remaining == 0 {
+                return (Outcome::Completed, tick, None);
+            }
+
+            // Schedule the next tick a whole number of seconds after start, which
+            // avoids the drift that repeated sleep(1s) calls would accumulate
+            tick += 1;
+            let mut target: Instant = start + Duration::from_secs(tick - done);
+            let mut paused_at: Option<Instant> = None;
+
+            // Wait until the target time (or, while paused, until resumed), handling
+            // controls as they arrive. If we're running late (system hiccup, suspend)
+            // the wait ends immediately and the next iteration catches up
+            loop {
+                if self.cancelled.load(Ordering::SeqCst) {
+                    return (Outcome::Cancelled, tick - 1, None);
+                }
+
+                let now: Instant = self.clock.now();
+                let wait = match paused_at {
+                    Some(_) => PAUSED_POLL,
+                    None if target <= now => break,
+                    None => target - now,
+                };
+
+                let control = match self.recv_timeout(controls, wait) {
+                    Ok(control) => control,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    // Nobody is sending controls: plain sleep until the tick
+                    Err(RecvTimeoutError::Disconnected) if paused_at.is_none() => {
+                        self.clock.sleep(wait);
+                        continue;
+                    }
+                    // Nobody can resume us any more, so behave as if they did
+                    Err(RecvTimeoutError::Disconnected) => Control::TogglePause,
+                };
+
+                match control {
+                    Control::TogglePause | Control::Pause | Control::Resume => {
+                        let pause = match control {
+                            Control::Pause => true,
+                            Control::Resume => false,
+                            _ => paused_at.is_none(),
+                        };
+                        match (paused_at, pause) {
+                            (Some(at), false) => {
+                                // Rebase the schedule so the time spent paused is not counted
+                                start += self.clock.now() - at;
+                                target = start + Duration::from_secs(tick - done);
+                                paused_at = None;
+                            }
+                            (None, true) => paused_at = Some(self.clock.now()),
+                            _ => continue, // Already in the requested state
+                        }
+                    }
+                    Control::Skip => return (Outcome::Skipped, tick - 1, None),
+                    Control::Restart => return (Outcome::Restarted, tick - 1, None),
+                    Control::Extend => total = total.saturating_add(60),
+                    // Shortening below the elapsed time ends the phase at the next tick
+                    Control::Shorten => total = total.saturating_sub(60),
+                    Control::Interrupt(_, _) | Control::Void(_) if phase.is_break() => continue,
+                    Control::Void(reason) => return (Outcome::Voided, tick - 1, reason),
+                    Control::Interrupt(kind, note) => observer.on_event(&Event::Interrupted {
+                        cycle,
+                        phase,
+                        kind,
+                        note,
+                    }),
+                }
+
+                // Report the effect of the control right away instead of at the next tick
+                let shown = total.saturating_sub(tick - 1);
+                observer.on_event(&tick_event(shown, total, paused_at.is_some()));
+            }
+        }
+    }
+
+    // Wait up to `wait` for the next control, measuring time with the session clock
+    // Polls in short slices so a fake clock can advance without any real sleeping
+    fn recv_timeout(
+        &self,
+        controls: &Receiver<Control>,
+        wait: Duration,
+    ) -> Result<Control, RecvTimeoutError> {
+        let deadline = self.clock.now() + wait;
+        loop {
+            match controls.try_recv() {
+                Ok(control) => return Ok(control),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => {}
+            }
+            let now = self.clock.now();
+            if now >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            self.clock.sleep((deadline - now).min(CONTROL_POLL));
+        }
+    }
+}
@@ -0,0 +1,337 @@
+//! Audible cues at phase boundaries, from the `[sounds]` section.
+//!
+//! Each cue names a sound: `"bell"` rings the terminal bell, anything else is
+//! a WAV or OGG file handed to an audio player. Where the sounds actually go is
+//! up to a [`Backend`]: the system one plays them, [`NullBackend`] drops them
+//! and [`FileSink`] writes a line per cue, which is what tests listen to.
+
+use crate::session::{Event, Observer, Outcome, Phase};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// Audio players tried in order when the config does not name one.
+const PLAYERS: [&str; 4] = ["paplay", "pw-play", "ffplay", "aplay"];
+
+/// The moments that can have a sound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cue {
+    FocusEnd,
+    BreakEnd,
+    SessionComplete,
+    /// Every second of a running focus phase
+    Tick,
+}
+
+impl Cue {
+    /// The key naming this cue in the `[sounds]` section.
+    pub fn name(self) -> &'static str {
+        match self {
+            Cue::FocusEnd => "focus-end",
+            Cue::BreakEnd => "break-end",
+            Cue::SessionComplete => "session-complete",
+            Cue::Tick => "tick",
+        }
+    }
+}
+
+impl fmt::Display for Cue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What a cue sounds like.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sound {
+    /// The terminal bell
+    Bell,
+    /// An audio file
+    File(PathBuf),
+}
+
+impl Sound {
+    /// Parse a cue's setting; an empty one means silence, and `~/` is the home directory.
+    pub fn parse(text: &str) -> Option<Sound> {
+        match text.trim() {
+            "" => None,
+            "bell" => Some(Sound::Bell),
+            path => match (path.strip_prefix("~/"), env::var_os("HOME")) {
+                (Some(rest), Some(home)) => Some(Sound::File(PathBuf::from(home).join(rest))),
+                _ => Some(Sound::File(PathBuf::from(path))),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Sound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sound::Bell => f.write_str("bell"),
+            Sound::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Where sounds go, picked with `backend` in the `[sounds]` section.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// The terminal bell and an audio player
+    #[default]
+    System,
+    /// Silence
+    None,
+    /// A line per cue in the `sink` file
+    File,
+}
+
+/// The `[sounds]` section of the config file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Sounds {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<BackendKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub focus_end: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub break_end: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_complete: Option<String>,
+    /// Off unless set, as it sounds every second
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tick: Option<String>,
+    /// Percent, from 0 to 100
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<u8>,
+    /// Command that plays a file, given as its last argument
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub player: Option<String>,
+    /// The file the `file` backend writes to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sink: Option<PathBuf>,
+    /// Keys that are not settings, kept so they can be reported
+    #[serde(flatten, skip_serializing)]
+    pub unknown: BTreeMap<String, toml::Value>,
+}
+
+impl Sounds {
+    /// The sound for `cue`: the bell at phase boundaries unless configured, nothing for ticks.
+    pub fn sound(&self, cue: Cue) -> Option<Sound> {
+        let setting = match cue {
+            Cue::FocusEnd => &self.focus_end,
+            Cue::BreakEnd => &self.break_end,
+            Cue::SessionComplete => &self.session_complete,
+            Cue::Tick => &self.tick,
+        };
+        match setting {
+            Some(text) => Sound::parse(text),
+            None if cue == Cue::Tick => None,
+            None => Some(Sound::Bell),
+        }
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume.unwrap_or(100).min(100)
+    }
+
+    /// The backend these settings ask for.
+    pub fn backend(&self) -> Result<Box<dyn Backend>, String> {
+        Ok(match self.backend.unwrap_or_default() {
+            BackendKind::System => Box::new(SystemBackend::new(self.player.clone())),
+            BackendKind::None => Box::new(NullBackend),
+            BackendKind::File => match &self.sink {
+                Some(path) => Box::new(FileSink::new(path)),
+                None => return Err("backend = \"file\" needs a sink path".to_string()),
+            },
+        })
+    }
+}
+
+/// Something that can make sounds.
+pub trait Backend: Send {
+    /// Play `sound` for `cue` at `volume` percent, without waiting for it to finish.
+    fn play(&mut self, cue: Cue, sound: &Sound, volume: u8) -> io::Result<()>;
+}
+
+/// Rings the terminal bell and plays files with an external player.
+pub struct SystemBackend {
+    player: Option<String>,
+}
+
+impl SystemBackend {
+    /// Play files with `player`, or the first of the usual players that is installed.
+    pub fn new(player: Option<String>) -> Self {
+        SystemBackend { player }
+    }
+
+    // The player command for `file`, with the volume passed the way each player takes it
+    fn command(&self, file: &Path, volume: u8) -> io::Result<Command> {
+        if let Some(player) = &self.player {
+            let mut command = Command::new("sh");
+            command
+                .arg("-c")
+                .arg(format!("{player} \"$1\""))
+                .arg("sh")
+                .arg(file)
+                .env("POMODORO_VOLUME", volume.to_string());
+            return Ok(command);
+        }
+        let Some(player) = PLAYERS.into_iter().find(|name| on_path(name)) else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no audio player found (tried {})", PLAYERS.join(", ")),
+            ));
+        };
+        let mut command = Command::new(player);
+        match player {
+            "paplay" => command.arg(format!("--volume={}", u32::from(volume) * 65536 / 100)),
+            "pw-play" => command.arg(format!("--volume={}", f32::from(volume) / 100.0)),
+            "ffplay" => command
+                .args(["-nodisp", "-autoexit", "-loglevel", "quiet", "-volume"])
+                .arg(volume.to_string()),
+            _ => command.arg("-q"),
+        };
+        command.arg(file);
+        Ok(command)
+    }
+}
+
+// Whether an executable called `name` is in one of the $PATH directories
+fn on_path(name: &str) -> bool {
+    env::var_os("PATH")
+        .is_some_and(|path| env::split_paths(&path).any(|dir| dir.join(name).is_file()))
+}
+
+impl Backend for SystemBackend {
+    fn play(&mut self, _cue: Cue, sound: &Sound, volume: u8) -> io::Result<()> {
+        match sound {
+            // The bell has no volume of its own, so all it can do is stay quiet
+            Sound::Bell if volume == 0 => Ok(()),
+            Sound::Bell => {
+                let mut stderr = io::stderr();
+                if stderr.is_terminal() {
+                    stderr.write_all(b"\x07")?;
+                }
+                Ok(())
+            }
+            Sound::File(file) => {
+                let mut child = self
+                    .command(file, volume)?
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()?;
+                // Reap the player once it is done, without holding up the timer
+                thread::spawn(move || child.wait());
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Plays nothing.
+pub struct NullBackend;
+
+impl Backend for NullBackend {
+    fn play(&mut self, _cue: Cue, _sound: &Sound, _volume: u8) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Appends `<cue> <sound> <volume>` to a file for every sound played.
+pub struct FileSink {
+    path: PathBuf,
+}
+
+impl FileSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileSink { path: path.into() }
+    }
+}
+
+impl Backend for FileSink {
+    fn play(&mut self, cue: Cue, sound: &Sound, volume: u8) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{cue} {sound} {volume}")
+    }
+}
+
+/// Observer that plays the configured sounds as the session goes.
+///
+/// A phase's end sound plays as soon as it ends, except for the last of the
+/// `cycles` focus phases: the session-complete sound that follows it plays
+/// instead, rather than both at once.
+pub struct SoundPlayer {
+    settings: Sounds,
+    backend: Box<dyn Backend>,
+    cycles: u64,
+    last_focus_end: bool,
+    warned: bool,
+}
+
+impl SoundPlayer {
+    pub fn new(settings: Sounds, backend: Box<dyn Backend>, cycles: u64) -> Self {
+        SoundPlayer {
+            settings,
+            backend,
+            cycles,
+            last_focus_end: false,
+            warned: false,
+        }
+    }
+
+    /// Play the focus-end sound for the last focus phase too, for when the
+    /// session waits on the user (such as to rate the pomodoro) before it ends.
+    pub fn with_last_focus_end(mut self) -> Self {
+        self.last_focus_end = true;
+        self
+    }
+
+    fn play(&mut self, cue: Cue) {
+        let Some(sound) = self.settings.sound(cue) else {
+            return;
+        };
+        if let Err(err) = self.backend.play(cue, &sound, self.settings.volume())
+            && !self.warned
+        {
+            eprintln!("\nwarning: cannot play the {cue} sound ({sound}): {err}");
+            self.warned = true;
+        }
+    }
+}
+
+impl Observer for SoundPlayer {
+    fn on_event(&mut self, event: &Event) {
+        match *event {
+            Event::PhaseCompleted {
+                cycle,
+                phase,
+                outcome: Outcome::Completed,
+                ..
+            } => match phase {
+                Phase::Focus if cycle >= self.cycles && !self.last_focus_end => {}
+                Phase::Focus => self.play(Cue::FocusEnd),
+                Phase::Break | Phase::LongBreak => self.play(Cue::BreakEnd),
+            },
+            Event::SessionCompleted => self.play(Cue::SessionComplete),
+            // The last tick of a phase is at 0:00, where its end sound plays instead
+            Event::Tick {
+                phase: Phase::Focus,
+                paused: false,
+                remaining,
+                ..
+            } if remaining > 0 => self.play(Cue::Tick),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! A snapshot of the running session, kept on disk so it can be resumed.
+//!
+//! [`StateSaver`] rewrites `~/.local/state/pomodoro/session.json` on every tick,
+//! so if the process dies the file says where it stopped to within a second.
+//! The snapshot is removed once the session completes; a cancelled or killed
+//! session leaves it behind for `pomodoro resume`.
+
+use crate::format::TimeFormat;
+use crate::history::Labels;
+use crate::session::{Event, Observer, Phase, Position, SessionConfig};
+use crate::status::Status;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Everything needed to carry on with a session from where it was saved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionState {
+    /// Process that was running the session
+    pub pid: u32,
+    /// Wall-clock time of the snapshot
+    pub saved_at: DateTime<Local>,
+    pub config: SessionConfig,
+    pub position: Position,
+    /// Whether the countdown was paused; time away then does not count
+    pub paused: bool,
+    /// Set when the session was cancelled: it can be resumed, but nothing is
+    /// running it, and like a paused one it does not count the time away
+    #[serde(default)]
+    pub stopped: bool,
+    /// The config profile the session was started with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub time_format: TimeFormat,
+    /// The task the session is spent on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    /// The number of that task
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<u64>,
+    /// The project and tags the session is filed under
+    #[serde(flatten)]
+    pub labels: Labels,
+}
+
+impl SessionState {
+    /// Where the session would be at `now` had it kept running since the snapshot,
+    /// or None if it would have finished by then.
+    ///
+    /// A paused or stopped session stays exactly where it was.
+    pub fn position_at(&self, now: DateTime<Local>) -> Option<Position> {
+        let mut position = self.position;
+        // A cancelled session recorded its partial phase when it stopped
+        if self.stopped {
+            position.reported = position.elapsed;
+        }
+        if self.paused || self.stopped {
+            return Some(position);
+        }
+
+        // A clock that went backwards counts as no time at all
+        let mut away = (now - self.saved_at).num_seconds().max(0) as u64;
+        loop {
+            let remaining = position.total.saturating_sub(position.elapsed);
+            if away < remaining {
+                position.elapsed += away;
+                return Some(position);
+            }
+            away -= remaining;
+            let (cycle, phase) = self.config.next_phase(position.cycle, position.phase)?;
+            position = Position {
+                cycle,
+                phase,
+                elapsed: 0,
+                total: self.config.duration_of(phase),
+                reported: 0,
+            };
+        }
+    }
+
+    /// The status the session would have at `now`, or None if it would be over.
+    pub fn status_at(&self, now: DateTime<Local>) -> Option<Status> {
+        let position = self.position_at(now)?;
+        Some(Status {
+            cycle: position.cycle,
+            cycles: self.config.cycles,
+            phase: position.phase,
+            remaining: position.total.saturating_sub(position.elapsed),
+            total: position.total,
+            paused: self.paused,
+        })
+    }
+
+    /// Whether another process is still running this session.
+    pub fn is_running(&self) -> bool {
+        if self.stopped || self.pid == process::id() {
+            return false;
+        }
+        // Signal 0 only checks that the process exists; EPERM means it does but
+        // belongs to someone else
+        // SAFETY: kill with signal 0 has no side effects
+        let result = unsafe { libc::kill(self.pid as libc::pid_t, 0) };
+        result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+}
+
+/// The state file.
+#[derive(Debug, Clone)]
+pub struct StateFile {
+    path: PathBuf,
+}
+
+impl StateFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        StateFile { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Replace the saved state with `state`.
+    ///
+    /// Writes to a temporary file and renames it over the old one, so a crash
+    /// mid-write leaves the previous snapshot intact rather than a torn one.
+    pub fn save(&self, state: &SessionState) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(state)?)?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    /// The saved state, or None if there is none.
+    pub fn load(&self) -> io::Result<Option<SessionState>> {
+        match fs::read(&self.path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Forget the saved state; removing a missing file is not an error.
+    pub fn remove(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Observer that keeps the state file up to date while a session runs.
+pub struct StateSaver {
+    file: StateFile,
+    config: SessionConfig,
+    profile: Option<String>,
+    time_format: TimeFormat,
+    task: Option<String>,
+    task_id: Option<u64>,
+    labels: Labels,
+    // The most recent snapshot, re-saved as stopped if the session is cancelled
+    last: Option<SessionState>,
+    reported_error: bool,
+}
+
+impl StateSaver {
+    pub fn new(
+        file: StateFile,
+        config: SessionConfig,
+        profile: Option<String>,
+        time_format: TimeFormat,
+    ) -> Self {
+        StateSaver {
+            file,
+            config,
+            profile,
+            time_format,
+            task: None,
+            task_id: None,
+            labels: Labels::default(),
+            last: None,
+            reported_error: false,
+        }
+    }
+
+    /// Save `task`, task number `id`, with the session, so a resumed session keeps it.
+    pub fn with_task(mut self, task: Option<String>, id: Option<u64>) -> Self {
+        self.task = task;
+        self.task_id = id;
+        self
+    }
+
+    /// Save `labels` with the session, so a resumed session keeps them.
+    pub fn with_labels(mut self, labels: Labels) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    fn save(&mut self, cycle: u64, phase: Phase, remaining: u64, total: u64, paused: bool) {
+        self.last = Some(SessionState {
+            pid: process::id(),
+            saved_at: Local::now(),
+            config: self.config.clone(),
+            position: Position {
+                cycle,
+                phase,
+                elapsed: total.saturating_sub(remaining),
+                total,
+                reported: 0,
+            },
+            paused,
+            stopped: false,
+            profile: self.profile.clone(),
+            time_format: self.time_format,
+            task: self.task.clone(),
+            task_id: self.task_id,
+            labels: self.labels.clone(),
+        });
+        self.write();
+    }
+
+    fn write(&mut self) {
+        if let Some(state) = &self.last {
+            let result = self.file.save(state);
+            self.report(result);
+        }
+    }
+
+    // Losing the ability to resume should not stop the timer; say so once
+    fn report(&mut self, result: io::Result<()>) {
+        if let Err(err) = result
+            && !self.reported_error
+        {
+            self.reported_error = true;
+            eprintln!(
+                "\nwarning: cannot save session state to {}: {err}",
+                self.file.path().display()
+            );
+        }
+    }
+}
+
+impl Observer for StateSaver {
+    fn on_event(&mut self, event: &Event) {
+        match *event {
+            Event::Tick {
+                cycle,
+                phase,
+                remaining,
+                total,
+                paused,
+            } => self.save(cycle, phase, remaining, total, paused),
+            Event::SessionCompleted => {
+                let result = self.file.remove();
+                self.report(result);
+            }
+            // The last tick saved where a cancelled session stopped; only mark it
+            // as no longer running, so it can still be resumed
+            Event::Cancelled { .. } => {
+                if let Some(state) = &mut self.last {
+                    state.stopped = true;
+                    state.saved_at = Local::now();
+                }
+                self.write();
+            }
+            Event::PhaseStarted { .. }
+            | Event::PhaseCompleted { .. }
+            | Event::Interrupted { .. } => {}
+        }
+    }
+}
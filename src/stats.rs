@@ -0,0 +1,546 @@
+//! Reports built from the session history: `pomodoro stats`.
+
+use crate::duration::format_duration;
+use crate::history::{Labels, PhaseRecord};
+use crate::session::{Interruption, Outcome, Phase};
+use chrono::{Datelike, Days, Local, NaiveDate, Timelike};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// An inclusive range of calendar days, in local time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DateRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl DateRange {
+    /// Just `day`.
+    pub fn day(day: NaiveDate) -> Self {
+        DateRange { from: day, to: day }
+    }
+
+    /// The Monday-to-Sunday week containing `day`.
+    pub fn week(day: NaiveDate) -> Self {
+        let from = day - Days::new(u64::from(day.weekday().num_days_from_monday()));
+        DateRange {
+            from,
+            to: from + Days::new(6),
+        }
+    }
+
+    /// The calendar month containing `day`.
+    pub fn month(day: NaiveDate) -> Self {
+        let from = day.with_day(1).unwrap_or(day);
+        let next_month = from
+            .checked_add_months(chrono::Months::new(1))
+            .unwrap_or(from);
+        DateRange {
+            from,
+            to: next_month.pred_opt().unwrap_or(from),
+        }
+    }
+
+    /// Parse `YYYY-MM-DD..YYYY-MM-DD`, or a single `YYYY-MM-DD`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let parse_date = |part: &str| {
+            NaiveDate::parse_from_str(part.trim(), "%Y-%m-%d").map_err(|err| {
+                format!(
+                    "invalid date '{}': {err} (expected YYYY-MM-DD)",
+                    part.trim()
+                )
+            })
+        };
+        let range = match text.split_once("..") {
+            Some((from, to)) => DateRange {
+                from: parse_date(from)?,
+                to: parse_date(to)?,
+            },
+            None => DateRange::day(parse_date(text)?),
+        };
+        if range.from > range.to {
+            return Err(format!("range starts after it ends: {text}"));
+        }
+        Ok(range)
+    }
+
+    pub fn contains(&self, day: NaiveDate) -> bool {
+        self.from <= day && day <= self.to
+    }
+}
+
+/// Totals for one day of the report.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DayStats {
+    pub date: NaiveDate,
+    /// Focus phases that ran to the end
+    pub completed: u64,
+    /// Every focus phase that was started, however it ended
+    pub attempted: u64,
+    /// Time spent in focus phases, completed or not, in seconds
+    pub focus_secs: u64,
+}
+
+/// What a report's focus time can be split by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    Project,
+    /// A phase with several tags counts towards each of them
+    Tag,
+    Task,
+}
+
+impl GroupBy {
+    pub const ALL: [GroupBy; 3] = [GroupBy::Project, GroupBy::Tag, GroupBy::Task];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GroupBy::Project => "project",
+            GroupBy::Tag => "tag",
+            GroupBy::Task => "task",
+        }
+    }
+
+    // The groups `record` belongs to; None stands for "not set"
+    fn keys(self, record: &PhaseRecord) -> Vec<Option<String>> {
+        match self {
+            GroupBy::Project => vec![record.labels.project.clone()],
+            GroupBy::Tag if record.labels.tags.is_empty() => vec![None],
+            GroupBy::Tag => record.labels.tags.iter().cloned().map(Some).collect(),
+            GroupBy::Task => vec![record.task.clone()],
+        }
+    }
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        GroupBy::ALL
+            .into_iter()
+            .find(|by| by.name() == s)
+            .ok_or_else(|| format!("cannot group by '{s}' (expected project, tag or task)"))
+    }
+}
+
+/// Totals for one project, tag or task of the report.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GroupStats {
+    /// None collects the focus phases that had no project, tag or task
+    pub name: Option<String>,
+    pub completed: u64,
+    pub attempted: u64,
+    pub focus_secs: u64,
+}
+
+/// The average focus rating of the pomodoros started in one hour of the day.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct HourRating {
+    /// 0-23, local time
+    pub hour: u32,
+    /// Completed pomodoros that were given a rating
+    pub rated: u64,
+    pub avg_rating: f64,
+}
+
+/// The average focus rating of the pomodoros spent on one task.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TaskRating {
+    /// None collects the rated pomodoros that had no task
+    pub task: Option<String>,
+    pub rated: u64,
+    pub avg_rating: f64,
+}
+
+// Running total of focus ratings
+#[derive(Default)]
+struct Ratings {
+    sum: u64,
+    count: u64,
+}
+
+impl Ratings {
+    fn add(&mut self, rating: u8) {
+        self.sum += u64::from(rating);
+        self.count += 1;
+    }
+
+    fn average(&self) -> f64 {
+        self.sum as f64 / self.count.max(1) as f64
+    }
+}
+
+/// Everything `pomodoro stats` reports for a date range.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    pub range: DateRange,
+    /// The project and tags the report is limited to; empty for all of the history
+    #[serde(skip_serializing_if = "Labels::is_empty")]
+    pub filter: Labels,
+    /// Completed pomodoros
+    pub completed: u64,
+    /// Focus phases started, however they ended
+    pub attempted: u64,
+    /// Focus phases voided after an interruption; they count as attempted only
+    pub voided: u64,
+    /// Total focus time in seconds
+    pub focus_secs: u64,
+    /// Share of started focus phases that were completed, from 0 to 1
+    pub completion_rate: f64,
+    /// Average number of interruptions logged per focus phase
+    pub avg_interruptions: f64,
+    /// Interruptions from the user's own head
+    pub internal_interruptions: u64,
+    /// Interruptions from someone or something else
+    pub external_interruptions: u64,
+    /// Most consecutive days with at least one completed pomodoro
+    pub longest_streak_days: u64,
+    /// Hour of the day (0-23) in which the most pomodoros were completed
+    pub best_hour: Option<u32>,
+    /// Completed pomodoros that were given a focus rating
+    pub rated: u64,
+    /// Average focus rating (1-5) of the rated pomodoros
+    pub avg_rating: Option<f64>,
+    /// Average focus rating per hour of the day, for hours with any ratings
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rating_by_hour: Vec<HourRating>,
+    /// Average focus rating per task, best first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rating_by_task: Vec<TaskRating>,
+    /// Per-day totals, for every day of the range that had any focus time
+    pub days: Vec<DayStats>,
+    /// What `groups` splits the focus time by
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_by: Option<GroupBy>,
+    /// Totals per project, tag or task, most focus time first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<GroupStats>,
+}
+
+// The focus phases a report for `range` and `filter` covers, each with whether
+// it is an attempt of its own. A resumed phase goes on with the attempt its
+// cancelled run made, the focus phase of the same cycle before it, so it only
+// counts when that run is outside the report.
+fn focus_records<'a>(
+    records: &'a [PhaseRecord],
+    range: DateRange,
+    filter: &'a Labels,
+) -> impl Iterator<Item = (&'a PhaseRecord, bool)> {
+    let covered = move |record: &PhaseRecord| {
+        record.phase == Phase::Focus
+            && range.contains(record.started_at.date_naive())
+            && record.labels.includes(filter)
+    };
+    records
+        .iter()
+        .enumerate()
+        .filter(move |(_, record)| covered(record))
+        .map(move |(index, record)| {
+            let original = records[..index]
+                .iter()
+                .rev()
+                .find(|earlier| earlier.phase == Phase::Focus && earlier.cycle == record.cycle);
+            (record, !record.resumed || !original.is_some_and(covered))
+        })
+}
+
+/// Build the report for `range` from the full history, counting only the
+/// phases filed under `filter` (see [`Labels::includes`]).
+pub fn report(records: &[PhaseRecord], range: DateRange, filter: &Labels) -> Report {
+    let mut days: BTreeMap<NaiveDate, DayStats> = BTreeMap::new();
+    let mut by_hour = [0u64; 24];
+    let (mut internal, mut external) = (0u64, 0u64);
+    let mut voided = 0u64;
+    let mut ratings = Ratings::default();
+    let mut hour_ratings: [Ratings; 24] = Default::default();
+    let mut task_ratings: BTreeMap<Option<String>, (Option<String>, Ratings)> = BTreeMap::new();
+
+    // Phases are attributed to the local day (and hour) they started in
+    for (record, attempt) in focus_records(records, range, filter) {
+        let date = record.started_at.date_naive();
+        let day = days.entry(date).or_insert_with(|| DayStats {
+            date,
+            ..DayStats::default()
+        });
+        day.attempted += u64::from(attempt);
+        day.focus_secs += record.actual_secs;
+        for interruption in &record.interruptions {
+            match interruption.kind {
+                Interruption::Internal => internal += 1,
+                Interruption::External => external += 1,
+            }
+        }
+        match record.outcome {
+            Outcome::Completed => {
+                day.completed += 1;
+                by_hour[record.started_at.hour() as usize] += 1;
+            }
+            Outcome::Voided => voided += 1,
+            _ => {}
+        }
+        if let Some(rating) = record
+            .rating
+            .filter(|_| record.outcome == Outcome::Completed)
+        {
+            ratings.add(rating);
+            hour_ratings[record.started_at.hour() as usize].add(rating);
+            // Tasks that differ only in case are the same task, under the first spelling
+            let key = record.task.as_deref().map(str::to_lowercase);
+            task_ratings
+                .entry(key)
+                .or_insert_with(|| (record.task.clone(), Ratings::default()))
+                .1
+                .add(rating);
+        }
+    }
+
+    let completed: u64 = days.values().map(|day| day.completed).sum();
+    let attempted: u64 = days.values().map(|day| day.attempted).sum();
+    let ratio = |n: u64, d: u64| if d == 0 { 0.0 } else { n as f64 / d as f64 };
+
+    // The earliest hour wins a tie
+    let best_hour = (0..24u32)
+        .filter(|&hour| by_hour[hour as usize] > 0)
+        .max_by_key(|&hour| (by_hour[hour as usize], std::cmp::Reverse(hour)));
+
+    let rating_by_hour = (0..24u32)
+        .zip(&hour_ratings)
+        .filter(|(_, ratings)| ratings.count > 0)
+        .map(|(hour, ratings)| HourRating {
+            hour,
+            rated: ratings.count,
+            avg_rating: ratings.average(),
+        })
+        .collect();
+    let mut rating_by_task: Vec<TaskRating> = task_ratings
+        .into_values()
+        .map(|(task, ratings)| TaskRating {
+            task,
+            rated: ratings.count,
+            avg_rating: ratings.average(),
+        })
+        .collect();
+    // Pomodoros without a task go last among tasks with the same rating
+    rating_by_task.sort_by(|a, b| {
+        b.avg_rating
+            .total_cmp(&a.avg_rating)
+            .then(a.task.is_none().cmp(&b.task.is_none()))
+            .then(a.task.cmp(&b.task))
+    });
+
+    Report {
+        range,
+        filter: filter.clone(),
+        completed,
+        attempted,
+        voided,
+        focus_secs: days.values().map(|day| day.focus_secs).sum(),
+        completion_rate: ratio(completed, attempted),
+        avg_interruptions: ratio(internal + external, attempted),
+        internal_interruptions: internal,
+        external_interruptions: external,
+        longest_streak_days: longest_streak(&days),
+        best_hour,
+        rated: ratings.count,
+        avg_rating: (ratings.count > 0).then(|| ratings.average()),
+        rating_by_hour,
+        rating_by_task,
+        days: days.into_values().collect(),
+        group_by: None,
+        groups: Vec::new(),
+    }
+}
+
+// Longest run of consecutive calendar days with a completed pomodoro
+fn longest_streak(days: &BTreeMap<NaiveDate, DayStats>) -> u64 {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for day in days.values().filter(|day| day.completed > 0) {
+        let consecutive = previous.and_then(|p| p.succ_opt()) == Some(day.date);
+        current = if consecutive { current + 1 } else { 1 };
+        longest = longest.max(current);
+        previous = Some(day.date);
+    }
+    longest
+}
+
+/// Today's date in local time.
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+impl Report {
+    /// Split the report's focus time by project, tag or task, from the same
+    /// history it was built from.
+    pub fn with_groups(mut self, records: &[PhaseRecord], by: GroupBy) -> Self {
+        let mut groups: BTreeMap<Option<String>, GroupStats> = BTreeMap::new();
+        for (record, attempt) in focus_records(records, self.range, &self.filter) {
+            for name in by.keys(record) {
+                // Names that differ only in case are the same group, under the first spelling
+                let key = name.as_deref().map(str::to_lowercase);
+                let group = groups.entry(key).or_insert_with(|| GroupStats {
+                    name,
+                    ..GroupStats::default()
+                });
+                group.attempted += u64::from(attempt);
+                group.focus_secs += record.actual_secs;
+                if record.outcome == Outcome::Completed {
+                    group.completed += 1;
+                }
+            }
+        }
+        self.groups = groups.into_values().collect();
+        // The unnamed group goes last among groups with the same time
+        self.groups.sort_by_key(|group| {
+            (
+                Reverse(group.focus_secs),
+                group.name.is_none(),
+                group.name.clone(),
+            )
+        });
+        self.group_by = Some(by);
+        self
+    }
+
+    /// The report as a plain-text table for the terminal.
+    pub fn to_table(&self) -> String {
+        let mut out = String::new();
+        let DateRange { from, to } = self.range;
+        let filter = if self.filter.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", self.filter)
+        };
+        if from == to {
+            let _ = writeln!(out, "Pomodoro stats for {from}{filter}");
+        } else {
+            let _ = writeln!(out, "Pomodoro stats for {from} to {to}{filter}");
+        }
+        out.push('\n');
+
+        let best_hour = match self.best_hour {
+            Some(hour) => format!("{hour:02}:00-{:02}:00", (hour + 1) % 24),
+            None => "-".to_string(),
+        };
+        let avg_rating = match self.avg_rating {
+            Some(rating) => format!("{rating:.1}/5 ({} rated)", self.rated),
+            None => "-".to_string(),
+        };
+        let rows = [
+            ("Completed pomodoros", self.completed.to_string()),
+            ("Voided pomodoros", self.voided.to_string()),
+            ("Total focus time", format_duration(self.focus_secs)),
+            (
+                "Completion rate",
+                format!("{:.0}%", self.completion_rate * 100.0),
+            ),
+            (
+                "Avg. interruptions",
+                format!(
+                    "{:.1} ({} internal, {} external)",
+                    self.avg_interruptions,
+                    self.internal_interruptions,
+                    self.external_interruptions
+                ),
+            ),
+            (
+                "Longest streak",
+                format!(
+                    "{} day{}",
+                    self.longest_streak_days,
+                    if self.longest_streak_days == 1 {
+                        ""
+                    } else {
+                        "s"
+                    }
+                ),
+            ),
+            ("Best time of day", best_hour),
+            ("Avg. focus rating", avg_rating),
+        ];
+        for (label, value) in rows {
+            let _ = writeln!(out, "  {label:<22}{value}");
+        }
+
+        // A per-day breakdown only makes sense for ranges longer than a day
+        if from != to && !self.days.is_empty() {
+            out.push('\n');
+            let _ = writeln!(
+                out,
+                "  {:<12}{:>10}{:>12}{:>12}",
+                "Date", "Pomodoros", "Focus", "Completion"
+            );
+            for day in &self.days {
+                let rate = day.completed as f64 / day.attempted.max(1) as f64;
+                let _ = writeln!(
+                    out,
+                    "  {:<12}{:>10}{:>12}{:>11.0}%",
+                    day.date.format("%a %m-%d").to_string(),
+                    day.completed,
+                    format_duration(day.focus_secs),
+                    rate * 100.0
+                );
+            }
+        }
+
+        if let Some(by) = self.group_by
+            && !self.groups.is_empty()
+        {
+            out.push('\n');
+            let heading = match by {
+                GroupBy::Project => "Project",
+                GroupBy::Tag => "Tag",
+                GroupBy::Task => "Task",
+            };
+            let _ = writeln!(
+                out,
+                "  {heading:<24}{:>10}{:>12}{:>8}",
+                "Pomodoros", "Focus", "Share"
+            );
+            for group in &self.groups {
+                let share = group.focus_secs as f64 / self.focus_secs.max(1) as f64;
+                let _ = writeln!(
+                    out,
+                    "  {:<24}{:>10}{:>12}{:>7.0}%",
+                    group.name.as_deref().unwrap_or("(none)"),
+                    group.completed,
+                    format_duration(group.focus_secs),
+                    share * 100.0
+                );
+            }
+        }
+
+        if !self.rating_by_hour.is_empty() {
+            out.push('\n');
+            let _ = writeln!(out, "  {:<24}{:>10}{:>12}", "Hour", "Rated", "Focus");
+            for hour in &self.rating_by_hour {
+                let _ = writeln!(
+                    out,
+                    "  {:<24}{:>10}{:>10.1}/5",
+                    format!("{:02}:00-{:02}:00", hour.hour, (hour.hour + 1) % 24),
+                    hour.rated,
+                    hour.avg_rating
+                );
+            }
+        }
+        if !self.rating_by_task.is_empty() {
+            out.push('\n');
+            let _ = writeln!(out, "  {:<24}{:>10}{:>12}", "Task", "Rated", "Focus");
+            for task in &self.rating_by_task {
+                let _ = writeln!(
+                    out,
+                    "  {:<24}{:>10}{:>10.1}/5",
+                    task.task.as_deref().unwrap_or("(none)"),
+                    task.rated,
+                    task.avg_rating
+                );
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,159 @@
+//! Where a running session is, and one-line renderings of it for status bars.
+//!
+//! A [`Status`] comes either from the daemon or from the state file that a
+//! foreground `pomodoro run` keeps up to date. [`StatusTemplate`] turns it into
+//! text such as `Focus 12:34 2/4` using `{placeholder}`s picked by the user.
+
+use crate::format::{TimeFormat, format_time};
+use crate::session::Phase;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// A snapshot of a running session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Status {
+    /// The focus phase number (1-based)
+    pub cycle: u64,
+    /// Number of focus phases in the session
+    pub cycles: u64,
+    pub phase: Phase,
+    /// Seconds left in the current phase
+    pub remaining: u64,
+    /// Length of the current phase in seconds
+    pub total: u64,
+    pub paused: bool,
+}
+
+impl Status {
+    /// Seconds of the current phase already done.
+    pub fn elapsed(&self) -> u64 {
+        self.total.saturating_sub(self.remaining)
+    }
+}
+
+// The values a status template can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Value {
+    Phase,
+    Remaining,
+    Elapsed,
+    Total,
+    Percent,
+    Cycle,
+    Cycles,
+    State,
+}
+
+// Placeholder names, in the order they are listed in error messages
+const PLACEHOLDERS: [(&str, Value); 8] = [
+    ("phase", Value::Phase),
+    ("remaining", Value::Remaining),
+    ("elapsed", Value::Elapsed),
+    ("total", Value::Total),
+    ("percent", Value::Percent),
+    ("cycle", Value::Cycle),
+    ("cycles", Value::Cycles),
+    ("state", Value::State),
+];
+
+// A piece of a parsed template
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Value(Value),
+}
+
+/// A line like `{phase} {remaining} {cycle}/{cycles}`, checked when it is parsed.
+///
+/// Placeholders: `{phase}` (Focus, Break, Long break), `{remaining}`, `{elapsed}`
+/// and `{total}` (in the chosen time format), `{percent}` of the phase done,
+/// `{cycle}`, `{cycles}` and `{state}` (running or paused). `{{` and `}}` stand
+/// for literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusTemplate {
+    parts: Vec<Part>,
+}
+
+impl FromStr for StatusTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let Some(end) = rest.find('}') else {
+                        return Err("unclosed '{' (write {{ for a literal brace)".into());
+                    };
+                    let name = &rest[..end];
+                    let Some(&(_, value)) = PLACEHOLDERS.iter().find(|(n, _)| *n == name) else {
+                        let names: Vec<&str> = PLACEHOLDERS.iter().map(|(n, _)| *n).collect();
+                        return Err(format!(
+                            "unknown placeholder {{{name}}} (expected one of: {})",
+                            names.join(", ")
+                        ));
+                    };
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Value(value));
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => return Err("unmatched '}' (write }} for a literal brace)".into()),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(StatusTemplate { parts })
+    }
+}
+
+impl StatusTemplate {
+    /// Fill in the template for `status`, showing times in `format`.
+    pub fn render(&self, status: &Status, format: TimeFormat) -> String {
+        let mut line = String::new();
+        for part in &self.parts {
+            let value = match *part {
+                Part::Text(ref text) => {
+                    line.push_str(text);
+                    continue;
+                }
+                Part::Value(value) => value,
+            };
+            match value {
+                Value::Phase => line.push_str(status.phase.label()),
+                Value::Remaining => {
+                    line.push_str(&format_time(status.remaining, status.total, format))
+                }
+                Value::Elapsed => {
+                    line.push_str(&format_time(status.elapsed(), status.total, format))
+                }
+                Value::Total => line.push_str(&format_time(status.total, status.total, format)),
+                Value::Percent => line.push_str(&format_time(
+                    status.remaining,
+                    status.total,
+                    TimeFormat::Percent,
+                )),
+                // Writing to a String cannot fail
+                Value::Cycle => write!(line, "{}", status.cycle).unwrap_or_default(),
+                Value::Cycles => write!(line, "{}", status.cycles).unwrap_or_default(),
+                Value::State => line.push_str(if status.paused { "paused" } else { "running" }),
+            }
+        }
+        line
+    }
+}
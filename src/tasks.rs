@@ -0,0 +1,226 @@
+//! Named tasks that pomodoros are attributed to.
+//!
+//! Tasks live in `~/.local/share/pomodoro/tasks.json`, each with an optional
+//! estimate of how many pomodoros it needs. The actual count is not stored: it
+//! is worked out from the history, where every focus phase run with `--task`
+//! carries the task's name, so it can never drift from what was recorded.
+
+use crate::history::PhaseRecord;
+use crate::session::{Outcome, Phase};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Something to spend pomodoros on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Task {
+    /// Short number to refer to the task by, as `3` or `#3`
+    pub id: u64,
+    pub name: String,
+    /// How many pomodoros the task was expected to take
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<u32>,
+    pub created_at: DateTime<Local>,
+    /// When the task was marked done; open tasks have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub done_at: Option<DateTime<Local>>,
+}
+
+impl Task {
+    pub fn is_done(&self) -> bool {
+        self.done_at.is_some()
+    }
+}
+
+/// Why a task operation failed.
+#[derive(Debug)]
+pub enum TaskError {
+    /// The task file could not be read or written
+    Io { path: PathBuf, source: io::Error },
+    /// No task has that number or name
+    NotFound(String),
+    /// An open task with that name already exists
+    Duplicate(String),
+}
+
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            TaskError::NotFound(key) => write!(f, "no task '{key}'"),
+            TaskError::Duplicate(name) => write!(f, "there is already a task '{name}'"),
+        }
+    }
+}
+
+impl Error for TaskError {}
+
+/// The task file.
+#[derive(Debug, Clone)]
+pub struct TaskStore {
+    path: PathBuf,
+}
+
+impl TaskStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        TaskStore { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn io_error(&self, source: io::Error) -> TaskError {
+        TaskError::Io {
+            path: self.path.clone(),
+            source,
+        }
+    }
+
+    /// Every task, oldest first; a missing file means no tasks yet.
+    pub fn load(&self) -> Result<Vec<Task>, TaskError> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|err| self.io_error(err.into())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(self.io_error(err)),
+        }
+    }
+
+    // Replace the file in one step, so a crash never leaves half a list behind
+    fn save(&self, tasks: &[Task]) -> Result<(), TaskError> {
+        let write = || {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let tmp = self.path.with_extension("json.tmp");
+            fs::write(&tmp, serde_json::to_string_pretty(tasks)?)?;
+            fs::rename(&tmp, &self.path)
+        };
+        write().map_err(|err| self.io_error(err))
+    }
+
+    /// Add an open task called `name`.
+    pub fn add(&self, name: &str, estimate: Option<u32>) -> Result<Task, TaskError> {
+        let mut tasks = self.load()?;
+        let name = name.trim();
+        if tasks
+            .iter()
+            .any(|task| !task.is_done() && task.name.eq_ignore_ascii_case(name))
+        {
+            return Err(TaskError::Duplicate(name.to_string()));
+        }
+        let task = Task {
+            id: tasks.iter().map(|task| task.id).max().unwrap_or(0) + 1,
+            name: name.to_string(),
+            estimate,
+            created_at: Local::now(),
+            done_at: None,
+        };
+        tasks.push(task.clone());
+        self.save(&tasks)?;
+        Ok(task)
+    }
+
+    /// The task `key` refers to; see [`find`].
+    pub fn get(&self, key: &str) -> Result<Task, TaskError> {
+        let tasks = self.load()?;
+        find(&tasks, key)
+            .cloned()
+            .ok_or_else(|| TaskError::NotFound(key.to_string()))
+    }
+
+    /// Change the task `key` refers to with `change` and save it.
+    pub fn update(&self, key: &str, change: impl FnOnce(&mut Task)) -> Result<Task, TaskError> {
+        let mut tasks = self.load()?;
+        let id = find(&tasks, key)
+            .map(|task| task.id)
+            .ok_or_else(|| TaskError::NotFound(key.to_string()))?;
+        let task = tasks
+            .iter_mut()
+            .find(|task| task.id == id)
+            .expect("found above");
+        change(task);
+        let task = task.clone();
+        self.save(&tasks)?;
+        Ok(task)
+    }
+}
+
+/// The task `key` refers to: a number (`3` or `#3`), or a name, ignoring case.
+///
+/// A name matches open tasks before done ones, so a task can be added again
+/// once an older one with the same name is done.
+pub fn find<'a>(tasks: &'a [Task], key: &str) -> Option<&'a Task> {
+    let key = key.trim();
+    if let Ok(id) = key.strip_prefix('#').unwrap_or(key).parse::<u64>()
+        && let Some(task) = tasks.iter().find(|task| task.id == id)
+    {
+        return Some(task);
+    }
+    let named = |task: &&Task| task.name.eq_ignore_ascii_case(key);
+    tasks
+        .iter()
+        .filter(named)
+        .find(|task| !task.is_done())
+        .or_else(|| tasks.iter().rfind(named))
+}
+
+/// A task with the pomodoros recorded for it, as `pomodoro task list` shows it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskSummary {
+    pub id: u64,
+    pub name: String,
+    pub estimate: Option<u32>,
+    /// Completed pomodoros
+    pub actual: u32,
+    pub done: bool,
+}
+
+/// Summarise `tasks` with their actual pomodoro counts from `records`.
+pub fn summaries(tasks: &[Task], records: &[PhaseRecord]) -> Vec<TaskSummary> {
+    let actual = actual_counts(records);
+    tasks
+        .iter()
+        .map(|task| TaskSummary {
+            id: task.id,
+            name: task.name.clone(),
+            estimate: task.estimate,
+            actual: actual.get(&TaskKey::Id(task.id)).copied().unwrap_or(0)
+                + actual
+                    .get(&TaskKey::Name(task.name.clone()))
+                    .copied()
+                    .unwrap_or(0),
+            done: task.is_done(),
+        })
+        .collect()
+}
+
+// What a history record says its task is
+#[derive(PartialEq, Eq, Hash)]
+enum TaskKey {
+    Id(u64),
+    // Records from before tasks were recorded by number
+    Name(String),
+}
+
+// Completed pomodoros per task, from the history
+fn actual_counts(records: &[PhaseRecord]) -> HashMap<TaskKey, u32> {
+    let mut counts = HashMap::new();
+    for record in records {
+        if (record.phase, record.outcome) != (Phase::Focus, Outcome::Completed) {
+            continue;
+        }
+        let key = match (record.task_id, &record.task) {
+            (Some(id), _) => TaskKey::Id(id),
+            (None, Some(name)) => TaskKey::Name(name.clone()),
+            (None, None) => continue,
+        };
+        *counts.entry(key).or_insert(0) += 1;
+    }
+    counts
+}
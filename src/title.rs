@@ -0,0 +1,115 @@
+//! The terminal's title and tab progress while a session runs.
+//!
+//! The title is set with OSC 0 to the phase and time left, and OSC 9;4 drives
+//! the progress indicator that terminals such as Windows Terminal, ConEmu,
+//! WezTerm and Ghostty show in the tab. The title the terminal had before is
+//! pushed onto its title stack at the start and popped again at the end;
+//! terminals without the stack keep the last title shown.
+
+use crate::format::{TimeFormat, format_time};
+use crate::session::{Event, Observer};
+use std::io::{self, Stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Save the title and icon name on the terminal's stack
+const PUSH: &str = "\x1b[22;0t";
+// Clear the tab progress and bring the saved title back
+const RESTORE: &str = "\x1b]9;4;0;0\x07\x1b[23;0t";
+
+// Set while the title on stdout is ours, so `restore` knows whether to put it back
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Put back the terminal title and clear the progress indicator if a
+/// [`TerminalTitle`] on stdout changed them.
+///
+/// Safe to call at any time and more than once, e.g. from a Ctrl+C handler.
+pub fn restore() {
+    if ACTIVE.swap(false, Ordering::SeqCst) {
+        let mut out = io::stdout();
+        out.write_all(RESTORE.as_bytes()).ok();
+        out.flush().ok();
+    }
+}
+
+/// Observer that keeps the terminal title and tab progress up to date.
+///
+/// The original title is restored when it is dropped. Writes go nowhere once
+/// one has failed, as for the other renderers.
+pub struct TerminalTitle<W: Write> {
+    out: W,
+    cycles: u64,
+    time_format: TimeFormat,
+    last: String,
+    failed: bool,
+}
+
+impl TerminalTitle<Stdout> {
+    /// Set the title of the terminal on stdout.
+    pub fn stdout(cycles: u64, time_format: TimeFormat) -> Self {
+        ACTIVE.store(true, Ordering::SeqCst);
+        TerminalTitle::new(io::stdout(), cycles, time_format)
+    }
+}
+
+impl<W: Write> TerminalTitle<W> {
+    pub fn new(out: W, cycles: u64, time_format: TimeFormat) -> Self {
+        let mut title = TerminalTitle {
+            out,
+            cycles,
+            time_format,
+            last: String::new(),
+            failed: false,
+        };
+        title.write(PUSH);
+        title
+    }
+
+    fn write(&mut self, text: &str) {
+        if !self.failed {
+            self.failed = self
+                .out
+                .write_all(text.as_bytes())
+                .and_then(|()| self.out.flush())
+                .is_err();
+        }
+    }
+}
+
+impl<W: Write> Observer for TerminalTitle<W> {
+    fn on_event(&mut self, event: &Event) {
+        let Event::Tick {
+            cycle,
+            phase,
+            remaining,
+            total,
+            paused,
+        } = *event
+        else {
+            return;
+        };
+        let time = format_time(remaining, total, self.time_format);
+        let state = if paused { " (paused)" } else { "" };
+        let title = format!(
+            "{} {time}{state} · {cycle}/{} · pomodoro",
+            phase.label(),
+            self.cycles
+        );
+        // Progress state 1 is normal, 4 is the "paused" (warning) colour
+        let percent = (total.saturating_sub(remaining) * 100)
+            .checked_div(total)
+            .unwrap_or(100);
+        let progress = if paused { 4 } else { 1 };
+        let sequence = format!("\x1b]0;{title}\x07\x1b]9;4;{progress};{percent}\x07");
+        if sequence != self.last {
+            self.write(&sequence);
+            self.last = sequence;
+        }
+    }
+}
+
+impl<W: Write> Drop for TerminalTitle<W> {
+    fn drop(&mut self) {
+        self.write(RESTORE);
+        ACTIVE.store(false, Ordering::SeqCst);
+    }
+}
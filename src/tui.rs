@@ -0,0 +1,390 @@
+//! The full-screen `--tui` display.
+//!
+//! Draws on the terminal's alternate screen with plain ANSI escape sequences:
+//! the remaining time in large block digits, a progress bar in the colour of
+//! the phase, a timeline of every phase in the session, today's completed
+//! pomodoros and the key hints. A background thread watches the terminal size
+//! and redraws as soon as it changes.
+
+use crate::format::{TimeFormat, format_time};
+use crate::session::{Event, Interruption, Observer, Outcome, Phase, SessionConfig};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How often the resize watcher checks the terminal size.
+const RESIZE_POLL: Duration = Duration::from_millis(100);
+
+// Set while the alternate screen is up, so `restore` knows whether to leave it
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+const ENTER: &str = "\x1b[?1049h\x1b[?25l"; // Alternate screen, hide cursor
+const LEAVE: &str = "\x1b[?25h\x1b[?1049l"; // Show cursor, back to the normal screen
+const RESET: &str = "\x1b[0m";
+const DIM: &str = "\x1b[2m";
+const BOLD: &str = "\x1b[1m";
+const REVERSE: &str = "\x1b[7m";
+
+/// Leave the alternate screen if the TUI is showing it.
+///
+/// Safe to call at any time and more than once, e.g. from a Ctrl+C handler.
+pub fn restore() {
+    if ACTIVE.swap(false, Ordering::SeqCst) {
+        let mut out = io::stdout();
+        out.write_all(LEAVE.as_bytes()).ok();
+        out.flush().ok();
+    }
+}
+
+/// The terminal size as (columns, rows), if stdout is a terminal.
+pub fn terminal_size() -> Option<(usize, usize)> {
+    // SAFETY: winsize is plain old data that TIOCGWINSZ fills in on success
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } != 0
+        || size.ws_col == 0
+    {
+        return None;
+    }
+    Some((size.ws_col as usize, size.ws_row as usize))
+}
+
+// 3x5 block font for the digits and the colon; '#' marks a filled cell
+const FONT: [[&str; 5]; 11] = [
+    ["###", "# #", "# #", "# #", "###"],
+    ["  #", "  #", "  #", "  #", "  #"],
+    ["###", "  #", "###", "#  ", "###"],
+    ["###", "  #", "###", "  #", "###"],
+    ["# #", "# #", "###", "  #", "  #"],
+    ["###", "#  ", "###", "  #", "###"],
+    ["###", "#  ", "###", "# #", "###"],
+    ["###", "  #", "  #", "  #", "  #"],
+    ["###", "# #", "###", "# #", "###"],
+    ["###", "# #", "###", "  #", "###"],
+    [" ", "#", " ", "#", " "],
+];
+
+/// Render `text` (digits and colons) as five rows of block characters, each
+/// font cell `scale` characters wide.
+pub fn big_text(text: &str, scale: usize) -> [String; 5] {
+    let mut rows: [String; 5] = Default::default();
+    for (i, c) in text.chars().enumerate() {
+        let glyph = match c {
+            '0'..='9' => &FONT[c as usize - '0' as usize],
+            ':' => &FONT[10],
+            _ => continue,
+        };
+        for (row, line) in rows.iter_mut().zip(glyph) {
+            if i > 0 {
+                row.push(' ');
+            }
+            for cell in line.chars() {
+                let fill = if cell == '#' { "█" } else { " " };
+                row.push_str(&fill.repeat(scale));
+            }
+        }
+    }
+    rows
+}
+
+// ANSI colour for each kind of phase
+fn color(phase: Phase) -> &'static str {
+    match phase {
+        Phase::Focus => "\x1b[31m",     // Red, like the tomato
+        Phase::Break => "\x1b[32m",     // Green
+        Phase::LongBreak => "\x1b[34m", // Blue
+    }
+}
+
+// A line of the frame: the text with escape codes, and how wide it shows
+struct Line {
+    text: String,
+    width: usize,
+}
+
+impl Line {
+    fn plain(text: impl Into<String>) -> Line {
+        let text = text.into();
+        Line {
+            width: text.chars().count(),
+            text,
+        }
+    }
+
+    fn styled(style: &str, text: &str) -> Line {
+        Line {
+            width: text.chars().count(),
+            text: format!("{style}{text}{RESET}"),
+        }
+    }
+}
+
+// Everything the screen shows
+struct Screen {
+    config: SessionConfig,
+    time_format: TimeFormat,
+    task: Option<String>,
+    cycle: u64,
+    phase: Phase,
+    remaining: u64,
+    total: u64,
+    paused: bool,
+    // Internal and external interruptions logged in this phase
+    interruptions: (u32, u32),
+    completed_today: u64,
+    size: Option<(usize, usize)>,
+}
+
+impl Screen {
+    // Every phase of the session in order, as (cycle, phase)
+    fn timeline(&self) -> Vec<(u64, Phase)> {
+        let mut phases = vec![(1, Phase::Focus)];
+        while let Some(next) = self
+            .config
+            .next_phase(phases[phases.len() - 1].0, phases[phases.len() - 1].1)
+        {
+            phases.push(next);
+        }
+        phases
+    }
+
+    fn lines(&self, cols: usize) -> Vec<Line> {
+        let tint = color(self.phase);
+        let mut lines = Vec::new();
+
+        let title = format!(
+            "{}  ·  session {}/{}",
+            self.phase.label(),
+            self.cycle,
+            self.config.cycles
+        );
+        lines.push(Line::styled(&format!("{BOLD}{tint}"), &title));
+        match &self.task {
+            Some(task) => lines.push(Line::plain(task.as_str())),
+            None => lines.push(Line::plain("")),
+        }
+
+        // The big clock, at double width when there is room for it
+        let clock = format_time(self.remaining, self.total, TimeFormat::Clock);
+        let scale = if big_text(&clock, 2)[0].chars().count() + 4 <= cols {
+            2
+        } else {
+            1
+        };
+        let big = big_text(&clock, scale);
+        if big[0].chars().count() <= cols {
+            for row in &big {
+                lines.push(Line::styled(tint, row));
+            }
+        } else {
+            lines.push(Line::styled(&format!("{BOLD}{tint}"), &clock));
+        }
+        lines.push(Line::plain(""));
+
+        // Progress bar with the share of the phase done
+        let done = self.total.saturating_sub(self.remaining);
+        let percent = format_time(self.remaining, self.total, TimeFormat::Percent);
+        let bar_width = cols.saturating_sub(12).clamp(10, 60);
+        let filled = if self.total == 0 {
+            bar_width
+        } else {
+            (done as usize * bar_width / self.total as usize).min(bar_width)
+        };
+        lines.push(Line {
+            text: format!(
+                "{tint}{}{RESET}{DIM}{}{RESET} {percent:>4}",
+                "█".repeat(filled),
+                "░".repeat(bar_width - filled)
+            ),
+            width: bar_width + 5,
+        });
+
+        // The remaining time in the user's chosen style, unless that is the clock already
+        let status = if self.paused { "⏸  PAUSED" } else { "" };
+        let detail = match self.time_format {
+            TimeFormat::Clock => status.to_string(),
+            format => format!(
+                "{} left  {status}",
+                format_time(self.remaining, self.total, format)
+            )
+            .trim_end()
+            .to_string(),
+        };
+        lines.push(Line::styled(BOLD, &detail));
+        let counts = match self.interruptions {
+            (0, 0) => String::new(),
+            (internal, external) => {
+                format!("Interruptions: {internal} internal · {external} external")
+            }
+        };
+        lines.push(Line::plain(counts));
+
+        // Timeline: ■ focus, · break, ◆ long break; done phases bright, the current
+        // one highlighted, the rest dimmed
+        let mut text = String::new();
+        let mut width = 0;
+        let timeline = self.timeline();
+        let current = timeline
+            .iter()
+            .position(|&slot| slot == (self.cycle, self.phase))
+            .unwrap_or(0);
+        for (i, &(_, phase)) in timeline.iter().enumerate() {
+            let mark = match phase {
+                Phase::Focus => "■",
+                Phase::Break => "·",
+                Phase::LongBreak => "◆",
+            };
+            let style = match i.cmp(&current) {
+                std::cmp::Ordering::Less => color(phase).to_string(),
+                std::cmp::Ordering::Equal => format!("{REVERSE}{}", color(phase)),
+                std::cmp::Ordering::Greater => DIM.to_string(),
+            };
+            if i > 0 {
+                text.push(' ');
+                width += 1;
+            }
+            text.push_str(&format!("{style}{mark}{RESET}"));
+            width += 1;
+        }
+        if width <= cols {
+            lines.push(Line { text, width });
+        }
+        lines.push(Line::plain(""));
+
+        lines.push(Line::plain(format!(
+            "Completed today: {}",
+            self.completed_today
+        )));
+        lines.push(Line::plain(""));
+        lines.push(Line::styled(
+            DIM,
+            "space pause · s skip · r restart · +/- 1 min · i/e interruption · v void · Ctrl+C quit",
+        ));
+        lines
+    }
+
+    // Draw the whole frame, centred, over whatever was there before
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let (cols, rows) = self.size.unwrap_or((80, 24));
+        let lines = self.lines(cols);
+        let top = rows.saturating_sub(lines.len()) / 2;
+
+        let mut frame = String::from("\x1b[H");
+        for row in 0..rows {
+            frame.push_str(&format!("\x1b[{};1H\x1b[2K", row + 1));
+            if let Some(line) = row.checked_sub(top).and_then(|i| lines.get(i)) {
+                let left = cols.saturating_sub(line.width) / 2;
+                frame.push_str(&" ".repeat(left));
+                frame.push_str(&line.text);
+            }
+        }
+        out.write_all(frame.as_bytes())?;
+        out.flush()
+    }
+}
+
+/// Observer that draws the session full-screen until it is dropped.
+pub struct TuiRenderer {
+    screen: Arc<Mutex<Screen>>,
+    stop: Arc<AtomicBool>,
+    watcher: Option<JoinHandle<()>>,
+}
+
+impl TuiRenderer {
+    /// Switch to the alternate screen and start watching for resizes.
+    ///
+    /// `task` is shown under the title. `completed_today` is the number of focus
+    /// phases already completed today; the count goes up as this session completes more.
+    pub fn start(
+        config: SessionConfig,
+        time_format: TimeFormat,
+        task: Option<String>,
+        completed_today: u64,
+    ) -> Self {
+        let screen = Arc::new(Mutex::new(Screen {
+            cycle: 1,
+            phase: Phase::Focus,
+            remaining: config.focus_secs,
+            total: config.focus_secs,
+            config,
+            time_format,
+            task,
+            paused: false,
+            interruptions: (0, 0),
+            completed_today,
+            size: terminal_size(),
+        }));
+
+        ACTIVE.store(true, Ordering::SeqCst);
+        let mut out = io::stdout();
+        out.write_all(ENTER.as_bytes()).ok();
+        out.flush().ok();
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let watcher = {
+            let screen = Arc::clone(&screen);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    thread::sleep(RESIZE_POLL);
+                    let mut screen = screen.lock().unwrap_or_else(PoisonError::into_inner);
+                    let size = terminal_size();
+                    if size != screen.size {
+                        screen.size = size;
+                        screen.draw(&mut io::stdout()).ok();
+                    }
+                }
+            })
+        };
+
+        TuiRenderer {
+            screen,
+            stop,
+            watcher: Some(watcher),
+        }
+    }
+}
+
+impl Observer for TuiRenderer {
+    fn on_event(&mut self, event: &Event) {
+        let mut screen = self.screen.lock().unwrap_or_else(PoisonError::into_inner);
+        match *event {
+            Event::Tick {
+                cycle,
+                phase,
+                remaining,
+                total,
+                paused,
+            } => {
+                screen.cycle = cycle;
+                screen.phase = phase;
+                screen.remaining = remaining;
+                screen.total = total;
+                screen.paused = paused;
+            }
+            Event::PhaseCompleted {
+                phase: Phase::Focus,
+                outcome: Outcome::Completed,
+                ..
+            } => screen.completed_today += 1,
+            Event::PhaseStarted { .. } => screen.interruptions = (0, 0),
+            Event::Interrupted { kind, .. } => match kind {
+                Interruption::Internal => screen.interruptions.0 += 1,
+                Interruption::External => screen.interruptions.1 += 1,
+            },
+            _ => return,
+        }
+        screen.draw(&mut io::stdout()).ok();
+    }
+}
+
+impl Drop for TuiRenderer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(watcher) = self.watcher.take() {
+            watcher.join().ok();
+        }
+        restore();
+    }
+}
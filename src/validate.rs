@@ -0,0 +1,138 @@
+//! Checks that a run configuration describes a schedule that can actually run.
+//!
+//! Hard problems (zero-length focus phases, no cycles) are [`ConfigError`]s that
+//! stop the run with a distinct exit code; durations too large to count in
+//! seconds are already refused by [`parse_duration`](crate::parse_duration). Combinations that work but are probably not what the user meant are
+//! reported as [`ConfigWarning`]s.
+
+use crate::session::SessionConfig;
+use std::error::Error;
+use std::fmt;
+
+/// Exit code for a value that is out of range, following sysexits' EX_USAGE.
+pub const EXIT_INVALID: i32 = 64;
+
+/// A setting of the run configuration, named after its command-line flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Focus,
+    Break,
+    LongBreak,
+    Cycles,
+    LongEvery,
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Field::Focus => "--focus",
+            Field::Break => "--break-min",
+            Field::LongBreak => "--long-break",
+            Field::Cycles => "--cycles",
+            Field::LongEvery => "--long-every",
+        })
+    }
+}
+
+/// A run configuration that cannot be used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The setting must not be zero
+    Zero { field: Field },
+}
+
+impl ConfigError {
+    /// The process exit code the CLI should use for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ConfigError::Zero { .. } => EXIT_INVALID,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Zero { field } => write!(f, "{field} must not be zero"),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// A run configuration that works but is probably a mistake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigWarning {
+    /// The long break comes after more focus phases than the session has
+    LongBreakNeverReached { long_every: u64, cycles: u64 },
+    /// The long break is shorter than the regular break
+    LongBreakShorterThanBreak,
+    /// Breaks of zero length, so phases run back to back
+    ZeroBreak { field: Field },
+}
+
+impl fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigWarning::LongBreakNeverReached { long_every, cycles } => write!(
+                f,
+                "--long-every {long_every} is more than --cycles {cycles}, so there will be no long break"
+            ),
+            ConfigWarning::LongBreakShorterThanBreak => {
+                write!(f, "--long-break is shorter than --break-min")
+            }
+            ConfigWarning::ZeroBreak { field } => {
+                write!(f, "{field} is 0, so phases will run back to back")
+            }
+        }
+    }
+}
+
+impl SessionConfig {
+    /// Check the whole configuration, returning any warnings if it is usable.
+    pub fn validate(&self) -> Result<Vec<ConfigWarning>, ConfigError> {
+        // A session needs at least one focus phase of at least one second, and
+        // `long_every` is used as a divisor when scheduling long breaks
+        if self.focus_secs == 0 {
+            return Err(ConfigError::Zero {
+                field: Field::Focus,
+            });
+        }
+        if self.cycles == 0 {
+            return Err(ConfigError::Zero {
+                field: Field::Cycles,
+            });
+        }
+        if self.long_every == 0 {
+            return Err(ConfigError::Zero {
+                field: Field::LongEvery,
+            });
+        }
+
+        let mut warnings = Vec::new();
+        if self.long_every > self.cycles {
+            warnings.push(ConfigWarning::LongBreakNeverReached {
+                long_every: self.long_every,
+                cycles: self.cycles,
+            });
+        }
+        // Breaks only matter when there is more than one focus phase
+        if self.cycles > 1 {
+            if self.break_secs == 0 {
+                warnings.push(ConfigWarning::ZeroBreak {
+                    field: Field::Break,
+                });
+            }
+            if self.long_every < self.cycles {
+                if self.long_break_secs == 0 {
+                    warnings.push(ConfigWarning::ZeroBreak {
+                        field: Field::LongBreak,
+                    });
+                } else if self.long_break_secs < self.break_secs {
+                    warnings.push(ConfigWarning::LongBreakShorterThanBreak);
+                }
+            }
+        }
+        Ok(warnings)
+    }
+}
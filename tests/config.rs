@@ -0,0 +1,92 @@
+// Config file parsing, profiles and settings precedence
+
+use pomodoro_cli::config::{self, ConfigFileError};
+use pomodoro_cli::{ConfigFile, RunSettings, SessionConfig, TimeFormat};
+use std::fs;
+
+const FILE: &str = r#"
+[defaults]
+cycles = 6
+time-format = "compact"
+
+[profiles.deep-work]
+focus = "50m"
+break-min = 10
+long-break = "1:00:00"
+"#;
+
+#[test]
+fn profile_is_layered_over_defaults() {
+    let file = ConfigFile::parse(FILE).unwrap();
+    let settings = file.settings(Some("deep-work")).unwrap();
+    assert_eq!(
+        settings.session_config(),
+        SessionConfig {
+            focus_secs: 50 * 60,
+            break_secs: 10 * 60,
+            long_break_secs: 3600,
+            cycles: 6,
+            long_every: 4,
+        }
+    );
+    assert_eq!(settings.time_format, Some(TimeFormat::Compact));
+    assert!(file.settings(Some("missing")).is_none());
+}
+
+#[test]
+fn command_line_wins_over_the_file() {
+    let file = ConfigFile::parse(FILE).unwrap();
+    let cli = RunSettings {
+        focus: Some(45 * 60),
+        cycles: Some(2),
+        ..RunSettings::default()
+    };
+    let config = cli
+        .or(file.settings(Some("deep-work")).unwrap())
+        .session_config();
+    assert_eq!(config.focus_secs, 45 * 60);
+    assert_eq!(config.cycles, 2);
+    assert_eq!(config.break_secs, 10 * 60);
+}
+
+#[test]
+fn built_in_defaults_fill_the_gaps() {
+    assert_eq!(
+        RunSettings::default().session_config(),
+        SessionConfig::default()
+    );
+}
+
+#[test]
+fn unknown_keys_are_reported_with_their_path() {
+    let file =
+        ConfigFile::parse("colour = 1\n[defaults]\nfcus = 5\n[profiles.a]\nx = true\n").unwrap();
+    assert_eq!(
+        file.unknown_keys(),
+        vec!["colour", "defaults.fcus", "profiles.a.x"]
+    );
+}
+
+#[test]
+fn bad_values_are_rejected() {
+    let err = ConfigFile::parse("[defaults]\nfocus = \"25 minutes\"\n").unwrap_err();
+    assert!(err.to_string().contains("invalid duration '25 minutes'"));
+    assert!(ConfigFile::parse("[defaults]\ntime-format = \"hours\"\n").is_err());
+    assert!(ConfigFile::parse("[defaults]\nfocus = -5\n").is_err());
+}
+
+#[test]
+fn missing_file_means_defaults_but_unknown_profile_is_an_error() {
+    let dir = std::env::temp_dir().join(format!("pomodoro-config-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+
+    let (settings, unknown) = config::load_settings(Some(&path), None).unwrap();
+    assert_eq!(settings, RunSettings::default());
+    assert!(unknown.is_empty());
+
+    fs::write(&path, FILE).unwrap();
+    let err = config::load_settings(Some(&path), Some("light")).unwrap_err();
+    assert!(matches!(err, ConfigFileError::UnknownProfile { .. }));
+    fs::remove_dir_all(&dir).unwrap();
+}
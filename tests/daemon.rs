@@ -0,0 +1,130 @@
+// The daemon and its line-delimited JSON protocol, driven through a real socket
+
+use pomodoro_cli::daemon::DaemonError;
+use pomodoro_cli::{
+    Client, Daemon, Event, Fanout, Interruption, Labels, Phase, Plan, Request, SessionConfig,
+};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// A fresh socket path inside a per-test temporary directory
+fn temp_socket(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pomodoro-daemon-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir.join("daemon.sock")
+}
+
+fn plan() -> Plan {
+    Plan {
+        config: SessionConfig {
+            focus_secs: 60,
+            break_secs: 30,
+            long_break_secs: 90,
+            cycles: 2,
+            long_every: 2,
+        },
+        profile: None,
+        time_format: Default::default(),
+        task: None,
+        task_id: None,
+        labels: Labels::default(),
+    }
+}
+
+#[test]
+fn requests_use_the_documented_wire_format() {
+    assert_eq!(
+        serde_json::to_string(&Request::Pause).unwrap(),
+        r#"{"command":"pause"}"#
+    );
+    let start: Request = serde_json::from_str(
+        r#"{"command":"start","config":{"focus_secs":60,"break_secs":30,"long_break_secs":90,"cycles":2,"long_every":2}}"#,
+    )
+    .unwrap();
+    assert_eq!(start, Request::Start(plan()));
+    let interrupt: Request =
+        serde_json::from_str(r#"{"command":"interrupt","kind":"external","note":"Slack ping"}"#)
+            .unwrap();
+    assert_eq!(
+        interrupt,
+        Request::Interrupt {
+            kind: Interruption::External,
+            note: Some("Slack ping".into())
+        }
+    );
+}
+
+#[test]
+fn clients_control_the_running_session() {
+    let socket = temp_socket("control");
+    let daemon = Daemon::bind(&socket).unwrap();
+
+    // Only one daemon may own the socket
+    assert!(matches!(
+        Daemon::bind(&socket),
+        Err(DaemonError::AlreadyRunning { .. })
+    ));
+
+    thread::spawn(move || daemon.serve());
+    let mut client = Client::connect(&socket).unwrap();
+
+    let idle = client.send(&Request::Status).unwrap();
+    assert!(idle.ok);
+    assert_eq!(idle.session, None);
+    let refused = client.send(&Request::Skip).unwrap();
+    assert!(!refused.ok);
+    assert_eq!(refused.error.as_deref(), Some("no session is running"));
+
+    assert!(client.send(&Request::Start(plan())).unwrap().ok);
+    assert!(!client.send(&Request::Start(plan())).unwrap().ok);
+    assert!(client.send(&Request::Pause).unwrap().ok);
+    thread::sleep(Duration::from_millis(200));
+
+    let status = client.send(&Request::Status).unwrap().session.unwrap();
+    assert_eq!((status.cycle, status.cycles), (1, 2));
+    assert_eq!(status.phase, Phase::Focus);
+    assert_eq!(status.total, 60);
+    assert!(status.paused);
+
+    assert!(client.send(&Request::Skip).unwrap().ok);
+    thread::sleep(Duration::from_millis(200));
+    let status = client.send(&Request::Status).unwrap().session.unwrap();
+    assert_eq!(status.phase, Phase::Break);
+
+    assert!(client.send(&Request::Stop).unwrap().ok);
+    assert_eq!(client.send(&Request::Status).unwrap().session, None);
+}
+
+#[test]
+fn shutdown_cancels_the_session_and_removes_the_socket() {
+    let socket = temp_socket("shutdown");
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&events);
+    let daemon = Daemon::bind(&socket)
+        .unwrap()
+        .with_observers(Box::new(move |_, _| {
+            let seen = Arc::clone(&seen);
+            let mut observers = Fanout::new();
+            observers.push(move |event: &Event| seen.lock().unwrap().push(event.clone()));
+            observers
+        }));
+    let shutdown = daemon.shutdown_handle();
+    thread::spawn(move || daemon.serve());
+
+    let mut client = Client::connect(&socket).unwrap();
+    assert!(client.send(&Request::Start(plan())).unwrap().ok);
+
+    // What a stop signal does before the daemon exits
+    shutdown.shutdown();
+    assert!(!socket.exists());
+    assert!(matches!(
+        events.lock().unwrap().last(),
+        Some(Event::Cancelled {
+            phase: Phase::Focus,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,86 @@
+// Duration syntax accepted by the duration flags
+
+use pomodoro_cli::duration::ParseDurationError;
+use pomodoro_cli::{format_duration, parse_duration};
+
+#[test]
+fn plain_numbers_are_minutes() {
+    assert_eq!(parse_duration("25"), Ok(25 * 60));
+    assert_eq!(parse_duration(" 5 "), Ok(5 * 60));
+}
+
+#[test]
+fn unit_suffixes() {
+    assert_eq!(parse_duration("90s"), Ok(90));
+    assert_eq!(parse_duration("25m"), Ok(25 * 60));
+    assert_eq!(parse_duration("2h"), Ok(2 * 3600));
+    assert_eq!(parse_duration("1h30m"), Ok(5400));
+    assert_eq!(parse_duration("1h5m30s"), Ok(3930));
+    assert_eq!(parse_duration("1h30s"), Ok(3630));
+}
+
+#[test]
+fn clock_notation() {
+    assert_eq!(parse_duration("25:00"), Ok(25 * 60));
+    assert_eq!(parse_duration("1:30:00"), Ok(5400));
+    assert_eq!(parse_duration("0:45"), Ok(45));
+    assert_eq!(parse_duration("90:00"), Ok(90 * 60));
+}
+
+#[test]
+fn rejects_malformed_input() {
+    assert_eq!(parse_duration(""), Err(ParseDurationError::Empty));
+    assert!(matches!(
+        parse_duration("1x"),
+        Err(ParseDurationError::BadUnit(_))
+    ));
+    assert!(matches!(
+        parse_duration("30m1h"),
+        Err(ParseDurationError::BadUnit(_))
+    ));
+    assert!(matches!(
+        parse_duration("5m5m"),
+        Err(ParseDurationError::BadUnit(_))
+    ));
+    assert!(matches!(
+        parse_duration("1h30"),
+        Err(ParseDurationError::BadUnit(_))
+    ));
+    assert!(matches!(
+        parse_duration("m"),
+        Err(ParseDurationError::InvalidNumber(_))
+    ));
+    assert!(matches!(
+        parse_duration("1:60"),
+        Err(ParseDurationError::OutOfRange(_))
+    ));
+    assert!(matches!(
+        parse_duration("1::00"),
+        Err(ParseDurationError::InvalidNumber(_))
+    ));
+    assert!(matches!(
+        parse_duration("-5"),
+        Err(ParseDurationError::InvalidNumber(_))
+    ));
+}
+
+#[test]
+fn rejects_overflow() {
+    assert_eq!(
+        parse_duration(&u64::MAX.to_string()),
+        Err(ParseDurationError::Overflow)
+    );
+    assert_eq!(
+        parse_duration("99999999999999999999s"),
+        Err(ParseDurationError::Overflow)
+    );
+}
+
+#[test]
+fn formats_back_into_the_same_syntax() {
+    for secs in [0, 45, 90, 25 * 60, 5400, 3930, 7200] {
+        assert_eq!(parse_duration(&format_duration(secs)), Ok(secs));
+    }
+    assert_eq!(format_duration(5400), "1h30m");
+    assert_eq!(format_duration(0), "0s");
+}
@@ -0,0 +1,44 @@
+// Remaining-time styles selectable with --time-format
+
+use pomodoro_cli::format::fmt_clock;
+use pomodoro_cli::{TimeFormat, format_time};
+
+#[test]
+fn clock_switches_to_hours_above_an_hour() {
+    assert_eq!(fmt_clock(0), "0:00");
+    assert_eq!(fmt_clock(125), "2:05");
+    assert_eq!(fmt_clock(3599), "59:59");
+    assert_eq!(fmt_clock(3600), "1:00:00");
+    assert_eq!(fmt_clock(9000), "2:30:00");
+}
+
+#[test]
+fn every_style() {
+    let total = 25 * 60;
+    assert_eq!(format_time(1499, total, TimeFormat::Clock), "24:59");
+    assert_eq!(format_time(1499, total, TimeFormat::Compact), "25m");
+    assert_eq!(format_time(1499, total, TimeFormat::Verbose), "24 min 59 s");
+    assert_eq!(format_time(900, total, TimeFormat::Percent), "40%");
+
+    assert_eq!(format_time(45, total, TimeFormat::Compact), "45s");
+    assert_eq!(format_time(5400, 5400, TimeFormat::Compact), "1h30m");
+    assert_eq!(format_time(7200, 7200, TimeFormat::Compact), "2h");
+    assert_eq!(
+        format_time(3725, 7200, TimeFormat::Verbose),
+        "1 h 2 min 5 s"
+    );
+    assert_eq!(format_time(0, 0, TimeFormat::Percent), "100%");
+}
+
+#[test]
+fn parses_style_names() {
+    for format in TimeFormat::ALL {
+        assert_eq!(format.name().parse(), Ok(format));
+    }
+    assert!(
+        "hours"
+            .parse::<TimeFormat>()
+            .unwrap_err()
+            .contains("clock, compact")
+    );
+}
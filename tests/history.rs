@@ -0,0 +1,162 @@
+// Session history: the on-disk store and the recorder observer
+
+use pomodoro_cli::{
+    Control, Event, Fanout, HistoryRecorder, HistoryStore, Labels, ManualClock, Outcome, Phase,
+    PhaseRecord, Reflection, Session, SessionConfig,
+};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+// A fresh history file path inside a per-test temporary directory
+fn temp_history(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pomodoro-history-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir.join("nested").join("history.jsonl")
+}
+
+#[test]
+fn recorder_writes_one_record_per_phase() {
+    let path = temp_history("recorder");
+    let store = HistoryStore::new(&path);
+    let config = SessionConfig {
+        focus_secs: 120,
+        break_secs: 60,
+        long_break_secs: 90,
+        cycles: 2,
+        long_every: 4,
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let mut observers = Fanout::new();
+    observers.push(HistoryRecorder::new(
+        store.clone(),
+        Some("deep-work".into()),
+    ));
+    // Skip the break halfway through
+    observers.push(move |event: &Event| {
+        if let Event::Tick {
+            phase: Phase::Break,
+            remaining: 30,
+            ..
+        } = *event
+        {
+            tx.send(Control::Skip).unwrap();
+        }
+    });
+    let outcome = Session::new(config)
+        .with_clock(ManualClock::new())
+        .run(&rx, &mut observers);
+    assert_eq!(outcome, Outcome::Completed);
+
+    let records = store.load().unwrap();
+    let summary: Vec<_> = records
+        .iter()
+        .map(|r| (r.cycle, r.phase, r.outcome, r.planned_secs, r.actual_secs))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (1, Phase::Focus, Outcome::Completed, 120, 120),
+            (1, Phase::Break, Outcome::Skipped, 60, 30),
+            (2, Phase::Focus, Outcome::Completed, 120, 120),
+        ]
+    );
+    assert!(
+        records
+            .iter()
+            .all(|r| r.profile.as_deref() == Some("deep-work"))
+    );
+    assert!(records.iter().all(|r| r.started_at <= r.ended_at));
+    fs::remove_dir_all(path.parent().unwrap().parent().unwrap()).unwrap();
+}
+
+#[test]
+fn reflections_are_kept_with_completed_pomodoros() {
+    let path = temp_history("reflect");
+    let store = HistoryStore::new(&path);
+    let config = SessionConfig {
+        focus_secs: 120,
+        break_secs: 60,
+        long_break_secs: 90,
+        cycles: 2,
+        long_every: 4,
+    };
+
+    let (_tx, rx) = mpsc::channel();
+    let mut observers = Fanout::new();
+    let reader = store.clone();
+    observers.push(
+        HistoryRecorder::new(store.clone(), None).with_reflection(Box::new(
+            move |record: &PhaseRecord| {
+                // Already written, so stopping during the question does not lose it
+                assert_eq!(reader.load().unwrap().last(), Some(record));
+                match record.cycle {
+                    1 => Reflection {
+                        rating: Some(4),
+                        note: Some("Drafted the release notes".into()),
+                    },
+                    _ => Reflection::default(),
+                }
+            },
+        )),
+    );
+    let outcome = Session::new(config)
+        .with_clock(ManualClock::new())
+        .run(&rx, &mut observers);
+    assert_eq!(outcome, Outcome::Completed);
+
+    let records = store.load().unwrap();
+    let reflections: Vec<_> = records
+        .iter()
+        .map(|r| (r.phase, r.rating, r.note.as_deref()))
+        .collect();
+    assert_eq!(
+        reflections,
+        vec![
+            (Phase::Focus, Some(4), Some("Drafted the release notes")),
+            (Phase::Break, None, None),
+            (Phase::Focus, None, None),
+        ]
+    );
+    fs::remove_dir_all(path.parent().unwrap().parent().unwrap()).unwrap();
+}
+
+#[test]
+fn missing_file_is_empty_and_torn_lines_are_skipped() {
+    let path = temp_history("torn");
+    let store = HistoryStore::new(&path);
+    assert!(store.load().unwrap().is_empty());
+
+    let record = pomodoro_cli::PhaseRecord {
+        started_at: chrono::Local::now(),
+        ended_at: chrono::Local::now(),
+        phase: Phase::Focus,
+        cycle: 1,
+        planned_secs: 1500,
+        actual_secs: 1500,
+        resumed: false,
+        outcome: Outcome::Completed,
+        reason: None,
+        pauses: 0,
+        profile: None,
+        task: None,
+        task_id: None,
+        labels: Labels::default(),
+        interruptions: Vec::new(),
+        rating: None,
+        note: None,
+    };
+    store.append(&record).unwrap();
+    // Simulate a crash in the middle of writing the next record
+    let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(b"{\"started_at\":\"2026-").unwrap();
+
+    assert_eq!(store.load().unwrap(), vec![record.clone()]);
+
+    // The next record still lands on a line of its own
+    store.append(&record).unwrap();
+    assert_eq!(store.load().unwrap(), vec![record.clone(), record]);
+    fs::remove_dir_all(path.parent().unwrap().parent().unwrap()).unwrap();
+}
@@ -0,0 +1,112 @@
+// Lifecycle hooks: the [hooks] config section and running the commands
+
+use pomodoro_cli::hooks::{HookError, run_hook};
+use pomodoro_cli::{ConfigFile, Fanout, HookRunner, Hooks, ManualClock, Session, SessionConfig};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+// A fresh file path inside a per-test temporary directory
+fn temp_file(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pomodoro-hooks-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.join("hooks.log")
+}
+
+#[test]
+fn hooks_section_is_parsed_and_checked() {
+    let file = ConfigFile::parse(
+        r#"
+        [hooks]
+        on-focus-start = "dnd on"
+        on-break-end = ""
+        timeout = "30s"
+        on-lunch = "eat"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(file.hooks.on_focus_start.as_deref(), Some("dnd on"));
+    assert_eq!(file.hooks.timeout(), Duration::from_secs(30));
+    assert_eq!(file.unknown_keys(), vec!["hooks.on-lunch"]);
+    assert!(!file.hooks.is_empty());
+    assert!(Hooks::default().is_empty());
+}
+
+#[test]
+fn failures_and_timeouts_are_reported() {
+    assert!(run_hook("true", &[], Duration::from_secs(5)).is_ok());
+
+    let err = run_hook("echo nope >&2; exit 3", &[], Duration::from_secs(5)).unwrap_err();
+    assert!(matches!(err, HookError::Failed(..)));
+    assert!(err.to_string().ends_with("(nope)"), "{err}");
+
+    // A child left running in the background keeps stderr open, but not the hook waiting
+    let started = Instant::now();
+    let err = run_hook(
+        "sleep 10 & echo nope >&2; exit 3",
+        &[],
+        Duration::from_secs(5),
+    )
+    .unwrap_err();
+    assert!(err.to_string().ends_with("(nope)"), "{err}");
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    // Nor does one that never stops writing to it
+    let started = Instant::now();
+    let err = run_hook("yes oops >&2 & exit 3", &[], Duration::from_secs(5)).unwrap_err();
+    // The last line may be cut off wherever the wait ended
+    assert!(
+        matches!(&err, HookError::Failed(_, line) if "oops".contains(line.as_str())),
+        "{err}"
+    );
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    let started = Instant::now();
+    let err = run_hook("sleep 10", &[], Duration::from_millis(200)).unwrap_err();
+    assert!(matches!(err, HookError::TimedOut(_)));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn hooks_run_at_each_transition_with_session_details() {
+    let log = temp_file("runner");
+    let append = format!(
+        "echo \"$POMODORO_EVENT $POMODORO_PHASE $POMODORO_CYCLE/$POMODORO_CYCLES $POMODORO_OUTCOME\" >> {}",
+        log.display()
+    );
+    let hooks = Hooks {
+        on_focus_start: Some(append.clone()),
+        on_focus_end: Some(append.clone()),
+        on_break_start: Some(append.clone()),
+        on_session_complete: Some(append),
+        ..Hooks::default()
+    };
+
+    let config = SessionConfig {
+        focus_secs: 2,
+        break_secs: 1,
+        long_break_secs: 1,
+        cycles: 2,
+        long_every: 4,
+    };
+    let (_tx, rx) = mpsc::channel();
+    let mut observers = Fanout::new();
+    observers.push(HookRunner::new(hooks, config.cycles, None));
+    Session::new(config)
+        .with_clock(ManualClock::new())
+        .run(&rx, &mut observers);
+    // Dropping the runner waits for every queued hook
+    drop(observers);
+
+    assert_eq!(
+        fs::read_to_string(&log).unwrap(),
+        "on-focus-start focus 1/2 \n\
+         on-focus-end focus 1/2 completed\n\
+         on-break-start break 1/2 \n\
+         on-focus-start focus 2/2 \n\
+         on-focus-end focus 2/2 completed\n\
+         on-session-complete  /2 \n"
+    );
+}
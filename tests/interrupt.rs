@@ -0,0 +1,101 @@
+// Interruptions: logged through controls, recorded with the phase, and passed
+// to a foreground session through the inbox
+
+use pomodoro_cli::interrupt::Inbox;
+use pomodoro_cli::{
+    Control, Event, Fanout, HistoryRecorder, HistoryStore, Interruption, ManualClock, Phase,
+    Session, SessionConfig,
+};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+// A fresh directory for one test's files
+fn temp_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("pomodoro-interrupt-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn interruptions_are_recorded_with_their_focus_phase() {
+    let dir = temp_dir("record");
+    let store = HistoryStore::new(dir.join("history.jsonl"));
+    let config = SessionConfig {
+        focus_secs: 60,
+        break_secs: 30,
+        long_break_secs: 30,
+        cycles: 2,
+        long_every: 4,
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let mut observers = Fanout::new();
+    observers.push(HistoryRecorder::new(store.clone(), None));
+    let mut logged = Vec::new();
+    observers.push(move |event: &Event| match *event {
+        // Once at the start of the first focus phase and of the break after it
+        Event::PhaseStarted {
+            cycle: 1, phase, ..
+        } => {
+            let note = Some(format!("during {}", phase.label()));
+            tx.send(Control::Interrupt(Interruption::External, note))
+                .unwrap();
+            if phase == Phase::Focus {
+                tx.send(Control::Interrupt(Interruption::Internal, None))
+                    .unwrap();
+            }
+        }
+        Event::Interrupted { phase, .. } => logged.push(phase),
+        Event::SessionCompleted => assert_eq!(logged, [Phase::Focus, Phase::Focus]),
+        _ => {}
+    });
+    Session::new(config)
+        .with_clock(ManualClock::new())
+        .run(&rx, &mut observers);
+
+    // The break ignored its interruption, and the next focus phase starts afresh
+    let records = store.load().unwrap();
+    let logged: Vec<_> = records
+        .iter()
+        .map(|record| {
+            record
+                .interruptions
+                .iter()
+                .map(|i| (i.kind, i.note.as_deref()))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    assert_eq!(
+        logged,
+        [
+            vec![
+                (Interruption::External, Some("during Focus")),
+                (Interruption::Internal, None)
+            ],
+            vec![],
+            vec![],
+        ]
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn the_inbox_hands_over_each_interruption_once() {
+    let dir = temp_dir("inbox");
+    let inbox = Inbox::new(dir.join("run").join("interrupts.jsonl"));
+    assert!(inbox.take().unwrap().is_empty());
+
+    let sent = [
+        Control::Interrupt(Interruption::External, Some("Slack ping".into())),
+        Control::Interrupt(Interruption::Internal, None),
+        Control::Void(Some("fire drill".into())),
+    ];
+    for control in &sent {
+        inbox.push(control).unwrap();
+    }
+    assert_eq!(inbox.take().unwrap(), sent);
+    assert!(inbox.take().unwrap().is_empty());
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,235 @@
+// Desktop notifications, against a private dbus-daemon with a stand-in notification server
+
+use pomodoro_cli::notify::{Urgency, fill};
+use pomodoro_cli::{ConfigFile, Control, Event, Notifications, Notifier, Observer, Outcome, Phase};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use zbus::blocking::Connection;
+use zbus::blocking::connection::Builder;
+use zbus::zvariant::OwnedValue;
+
+// What the stand-in server was asked to show: replaces id, summary, body, actions, urgency
+type Shown = Arc<Mutex<Vec<(u32, String, String, Vec<String>, u8)>>>;
+
+struct FakeServer {
+    shown: Shown,
+}
+
+#[zbus::interface(name = "org.freedesktop.Notifications")]
+impl FakeServer {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        _app_name: String,
+        replaces_id: u32,
+        _app_icon: String,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        hints: HashMap<String, OwnedValue>,
+        _expire_timeout: i32,
+    ) -> u32 {
+        let urgency = hints
+            .get("urgency")
+            .and_then(|value| u8::try_from(value).ok())
+            .unwrap_or(255);
+        let mut shown = self.shown.lock().unwrap();
+        shown.push((replaces_id, summary, body, actions, urgency));
+        // Like real servers, keep the id when replacing
+        if replaces_id == 0 { 42 } else { replaces_id }
+    }
+
+    fn close_notification(&self, _id: u32) {}
+}
+
+// A private bus, killed when the test ends
+struct Bus {
+    daemon: Child,
+    address: String,
+}
+
+impl Bus {
+    // None when there is no dbus-daemon to run the test against
+    fn start() -> Option<Bus> {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take()?)
+            .read_line(&mut address)
+            .ok()?;
+        Some(Bus {
+            daemon,
+            address: address.trim().to_string(),
+        })
+    }
+
+    // Run the stand-in server on the bus, returning its connection and what it shows
+    fn serve(&self) -> (Connection, Shown) {
+        let shown = Shown::default();
+        let server = Builder::address(self.address.as_str())
+            .unwrap()
+            .name("org.freedesktop.Notifications")
+            .unwrap()
+            .serve_at(
+                "/org/freedesktop/Notifications",
+                FakeServer {
+                    shown: Arc::clone(&shown),
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        (server, shown)
+    }
+
+    fn connect(&self) -> Connection {
+        Builder::address(self.address.as_str())
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+}
+
+// Wait until the server has shown `count` notifications
+fn wait_for(shown: &Shown, count: usize) {
+    let started = Instant::now();
+    while shown.lock().unwrap().len() < count {
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "nothing was shown"
+        );
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        self.daemon.kill().ok();
+        self.daemon.wait().ok();
+    }
+}
+
+#[test]
+fn notifications_section_is_parsed_and_templates_filled() {
+    let file = ConfigFile::parse(
+        r#"
+        [notifications]
+        urgency = "critical"
+        summary = "{ended} over"
+        expire = "5s"
+        sound = "ding"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(file.notifications.urgency, Some(Urgency::Critical));
+    assert_eq!(file.notifications.expire, Some(5));
+    assert_eq!(file.unknown_keys(), vec!["notifications.sound"]);
+
+    let values = [("ended", "Focus".to_string()), ("cycle", "2".to_string())];
+    assert_eq!(
+        fill("{ended} done, {cycle} {other}", &values),
+        "Focus done, 2 {other}"
+    );
+}
+
+#[test]
+fn phase_changes_notify_and_actions_control_the_session() {
+    let Some(bus) = Bus::start() else {
+        eprintln!("skipping: dbus-daemon is not available");
+        return;
+    };
+    let (_server, shown) = bus.serve();
+
+    let (controls, rx) = mpsc::channel();
+    let settings = Notifications {
+        urgency: Some(Urgency::Low),
+        ..Notifications::default()
+    };
+    let mut notifier = Notifier::new(bus.connect(), settings, 4, controls);
+    notifier.on_event(&Event::PhaseCompleted {
+        cycle: 1,
+        phase: Phase::Focus,
+        outcome: Outcome::Completed,
+        elapsed: 1500,
+        reason: None,
+    });
+    notifier.on_event(&Event::PhaseStarted {
+        cycle: 1,
+        phase: Phase::Break,
+        duration: 300,
+        reported: 0,
+    });
+
+    wait_for(&shown, 1);
+    assert_eq!(
+        shown.lock().unwrap()[0],
+        (
+            0,
+            "Focus done".to_string(),
+            "Break for 5m (session 1/4)".to_string(),
+            ["start", "Start break", "skip", "Skip"]
+                .map(String::from)
+                .to_vec(),
+            0
+        )
+    );
+
+    // Clicking "Skip" on the notification skips the break; the subscription may
+    // take a moment to be in place, so keep clicking until the click lands
+    let server = bus.connect();
+    let control = (0..50).find_map(|_| {
+        server
+            .emit_signal(
+                None::<&str>,
+                "/org/freedesktop/Notifications",
+                "org.freedesktop.Notifications",
+                "ActionInvoked",
+                &(42u32, "skip"),
+            )
+            .unwrap();
+        rx.recv_timeout(Duration::from_millis(100)).ok()
+    });
+    assert_eq!(control, Some(Control::Skip));
+
+    // Dropping the notifier shuts down its threads
+    drop(notifier);
+}
+
+#[test]
+fn session_end_counts_the_pomodoros_completed() {
+    let Some(bus) = Bus::start() else {
+        eprintln!("skipping: dbus-daemon is not available");
+        return;
+    };
+    let (_server, shown) = bus.serve();
+
+    let (controls, _rx) = mpsc::channel();
+    let mut notifier = Notifier::new(bus.connect(), Notifications::default(), 2, controls);
+    // The first pomodoro runs to its end, the last one is skipped
+    for (cycle, outcome) in [(1, Outcome::Completed), (2, Outcome::Skipped)] {
+        notifier.on_event(&Event::PhaseCompleted {
+            cycle,
+            phase: Phase::Focus,
+            outcome,
+            elapsed: 1500,
+            reason: None,
+        });
+    }
+    notifier.on_event(&Event::SessionCompleted);
+
+    wait_for(&shown, 1);
+    let (_, summary, body, actions, _) = shown.lock().unwrap()[0].clone();
+    assert_eq!(summary, "All sessions done");
+    assert_eq!(body, "1 of 2 pomodoros completed. Nice work.");
+    assert!(actions.is_empty());
+    drop(notifier);
+}
@@ -0,0 +1,64 @@
+// The newline-delimited JSON event stream
+
+use pomodoro_cli::{Event, JsonRenderer, ManualClock, Observer, Phase, Session, SessionConfig};
+use serde_json::Value;
+use std::sync::mpsc;
+
+#[test]
+fn events_serialize_with_their_stream_names() {
+    let tick = Event::Tick {
+        cycle: 1,
+        phase: Phase::LongBreak,
+        remaining: 59,
+        total: 60,
+        paused: true,
+    };
+    assert_eq!(
+        serde_json::to_string(&tick).unwrap(),
+        r#"{"event":"tick","cycle":1,"phase":"long_break","remaining":59,"total":60,"paused":true}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&Event::SessionCompleted).unwrap(),
+        r#"{"event":"session_end"}"#
+    );
+}
+
+#[test]
+fn a_session_streams_one_object_per_line() {
+    let config = SessionConfig {
+        focus_secs: 2,
+        break_secs: 1,
+        long_break_secs: 1,
+        cycles: 1,
+        long_every: 4,
+    };
+    let mut out = Vec::new();
+    let (_tx, rx) = mpsc::channel();
+    {
+        let mut renderer = JsonRenderer::new(&mut out);
+        Session::new(config)
+            .with_clock(ManualClock::new())
+            .run(&rx, &mut |event: &Event| renderer.on_event(event));
+    }
+
+    let lines: Vec<Value> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let names: Vec<&str> = lines.iter().map(|l| l["event"].as_str().unwrap()).collect();
+    assert_eq!(
+        names,
+        [
+            "phase_start",
+            "tick",
+            "tick",
+            "tick",
+            "phase_end",
+            "session_end"
+        ]
+    );
+    assert!(lines.iter().all(|line| line["time"].is_string()));
+    assert_eq!(lines[2]["remaining"], 1);
+    assert_eq!(lines[4]["outcome"], "completed");
+}
@@ -0,0 +1,375 @@
+// Session engine tests driven by a manual clock
+// Every test runs hours of simulated Pomodoro time in a few milliseconds of real time
+
+use pomodoro_cli::{Clock, Control, Event, ManualClock, Outcome, Phase, Session, SessionConfig};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const MIN: u64 = 60;
+
+fn config(cycles: u64, long_every: u64) -> SessionConfig {
+    SessionConfig {
+        focus_secs: 25 * MIN,
+        break_secs: 5 * MIN,
+        long_break_secs: 15 * MIN,
+        cycles,
+        long_every,
+    }
+}
+
+// Run a session to the end on a fresh manual clock, collecting every event
+// `script` is called for each event and may send controls back to the session
+fn run_with(
+    config: SessionConfig,
+    mut script: impl FnMut(&Event, &Sender<Control>),
+) -> (Outcome, Vec<Event>, Arc<ManualClock>) {
+    let clock = Arc::new(ManualClock::new());
+    let (tx, rx) = mpsc::channel();
+    let mut events = Vec::new();
+    let outcome =
+        Session::new(config)
+            .with_clock(Arc::clone(&clock))
+            .run(&rx, &mut |event: &Event| {
+                script(event, &tx);
+                events.push(event.clone());
+            });
+    (outcome, events, clock)
+}
+
+// The (cycle, phase) of every PhaseStarted event, in order
+fn started(events: &[Event]) -> Vec<(u64, Phase)> {
+    events
+        .iter()
+        .filter_map(|event| match *event {
+            Event::PhaseStarted { cycle, phase, .. } => Some((cycle, phase)),
+            _ => None,
+        })
+        .collect()
+}
+
+// The (phase, outcome, elapsed) of every PhaseCompleted event, in order
+fn completed(events: &[Event]) -> Vec<(Phase, Outcome, u64)> {
+    events
+        .iter()
+        .filter_map(|event| match *event {
+            Event::PhaseCompleted {
+                phase,
+                outcome,
+                elapsed,
+                ..
+            } => Some((phase, outcome, elapsed)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn full_session_schedules_long_breaks() {
+    let real_start = Instant::now();
+    let (outcome, events, clock) = run_with(config(8, 4), |_, _| {});
+
+    assert_eq!(outcome, Outcome::Completed);
+    assert_eq!(events.last(), Some(&Event::SessionCompleted));
+
+    use Phase::*;
+    assert_eq!(
+        started(&events),
+        vec![
+            (1, Focus),
+            (1, Break),
+            (2, Focus),
+            (2, Break),
+            (3, Focus),
+            (3, Break),
+            (4, Focus),
+            (4, LongBreak),
+            (5, Focus),
+            (5, Break),
+            (6, Focus),
+            (6, Break),
+            (7, Focus),
+            (7, Break),
+            (8, Focus),
+        ]
+    );
+
+    // 8 focus phases, 6 short breaks and 1 long break, without a break at the end
+    let expected = 8 * 25 * MIN + 6 * 5 * MIN + 15 * MIN;
+    assert_eq!(clock.elapsed(), Duration::from_secs(expected));
+    assert!(real_start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn single_cycle_has_no_break() {
+    let (outcome, events, clock) = run_with(config(1, 4), |_, _| {});
+    assert_eq!(outcome, Outcome::Completed);
+    assert_eq!(started(&events), vec![(1, Phase::Focus)]);
+    assert_eq!(clock.elapsed(), Duration::from_secs(25 * MIN));
+}
+
+#[test]
+fn ticks_count_down_every_second() {
+    let mut cfg = config(1, 4);
+    cfg.focus_secs = 3;
+    let (_, events, _) = run_with(cfg, |_, _| {});
+    let remaining: Vec<u64> = events
+        .iter()
+        .filter_map(|event| match *event {
+            Event::Tick { remaining, .. } => Some(remaining),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(remaining, vec![3, 2, 1, 0]);
+}
+
+#[test]
+fn skip_moves_on_to_the_next_phase() {
+    let (outcome, events, clock) = run_with(config(2, 4), |event, tx| {
+        // Skip the first focus phase ten minutes in
+        if let Event::Tick {
+            cycle: 1,
+            phase: Phase::Focus,
+            remaining,
+            ..
+        } = *event
+            && remaining == 15 * MIN
+        {
+            tx.send(Control::Skip).unwrap();
+        }
+    });
+
+    assert_eq!(outcome, Outcome::Completed);
+    assert_eq!(
+        completed(&events),
+        vec![
+            (Phase::Focus, Outcome::Skipped, 10 * MIN),
+            (Phase::Break, Outcome::Completed, 5 * MIN),
+            (Phase::Focus, Outcome::Completed, 25 * MIN),
+        ]
+    );
+    assert_eq!(clock.elapsed(), Duration::from_secs(40 * MIN));
+}
+
+#[test]
+fn restart_runs_the_phase_again() {
+    let mut restarted = false;
+    let (outcome, events, clock) = run_with(config(1, 4), |event, tx| {
+        if let Event::Tick { remaining, .. } = *event
+            && remaining == 20 * MIN
+            && !restarted
+        {
+            restarted = true;
+            tx.send(Control::Restart).unwrap();
+        }
+    });
+
+    assert_eq!(outcome, Outcome::Completed);
+    assert_eq!(started(&events), vec![(1, Phase::Focus), (1, Phase::Focus)]);
+    assert_eq!(
+        completed(&events),
+        vec![
+            (Phase::Focus, Outcome::Restarted, 5 * MIN),
+            (Phase::Focus, Outcome::Completed, 25 * MIN),
+        ]
+    );
+    assert_eq!(clock.elapsed(), Duration::from_secs(30 * MIN));
+}
+
+#[test]
+fn void_runs_the_same_focus_slot_again() {
+    let mut voided = false;
+    let (outcome, events, clock) = run_with(config(2, 4), |event, tx| match *event {
+        // Only a focus phase can be voided, so this one is ignored
+        Event::Tick {
+            phase: Phase::Break,
+            remaining,
+            ..
+        } if remaining == 4 * MIN => tx.send(Control::Void(None)).unwrap(),
+        Event::Tick {
+            cycle: 2,
+            remaining,
+            ..
+        } if remaining == 20 * MIN && !voided => {
+            voided = true;
+            tx.send(Control::Void(Some("fire drill".into()))).unwrap();
+        }
+        _ => {}
+    });
+
+    assert_eq!(outcome, Outcome::Completed);
+    assert_eq!(
+        started(&events),
+        vec![
+            (1, Phase::Focus),
+            (1, Phase::Break),
+            (2, Phase::Focus),
+            (2, Phase::Focus)
+        ]
+    );
+    assert_eq!(
+        completed(&events),
+        vec![
+            (Phase::Focus, Outcome::Completed, 25 * MIN),
+            (Phase::Break, Outcome::Completed, 5 * MIN),
+            (Phase::Focus, Outcome::Voided, 5 * MIN),
+            (Phase::Focus, Outcome::Completed, 25 * MIN),
+        ]
+    );
+    assert!(events.contains(&Event::PhaseCompleted {
+        cycle: 2,
+        phase: Phase::Focus,
+        outcome: Outcome::Voided,
+        elapsed: 5 * MIN,
+        reason: Some("fire drill".into()),
+    }));
+    assert_eq!(clock.elapsed(), Duration::from_secs(60 * MIN));
+}
+
+#[test]
+fn extend_and_shorten_change_the_phase_length() {
+    let (_, events, _) = run_with(config(1, 4), |event, tx| {
+        if let Event::PhaseStarted { .. } = event {
+            // Queued before the first tick: +2 minutes, then -1 minute
+            tx.send(Control::Extend).unwrap();
+            tx.send(Control::Extend).unwrap();
+            tx.send(Control::Shorten).unwrap();
+        }
+    });
+    assert_eq!(
+        completed(&events),
+        vec![(Phase::Focus, Outcome::Completed, 26 * MIN)]
+    );
+}
+
+#[test]
+fn shortening_past_the_elapsed_time_ends_the_phase() {
+    let mut cfg = config(1, 4);
+    cfg.focus_secs = 90;
+    let (_, events, _) = run_with(cfg, |event, tx| {
+        if let Event::Tick { remaining: 30, .. } = *event {
+            tx.send(Control::Shorten).unwrap();
+        }
+    });
+    assert_eq!(
+        completed(&events),
+        vec![(Phase::Focus, Outcome::Completed, 61)]
+    );
+}
+
+// A clock that resumes a paused session after a fixed amount of simulated time
+// Sleeping is the only thing a paused session does, so that is where we hook in
+struct ResumingClock {
+    clock: Arc<ManualClock>,
+    controls: Sender<Control>,
+    paused: Arc<AtomicBool>,
+    pause_for: Duration,
+    paused_since: Mutex<Option<Instant>>,
+}
+
+impl Clock for ResumingClock {
+    fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.clock.sleep(duration);
+        let mut since = self.paused_since.lock().unwrap();
+        if !self.paused.load(Ordering::SeqCst) {
+            *since = None;
+            return;
+        }
+        let started = *since.get_or_insert(self.clock.now());
+        if self.clock.now() - started >= self.pause_for {
+            self.paused.store(false, Ordering::SeqCst);
+            self.controls.send(Control::TogglePause).unwrap();
+        }
+    }
+}
+
+#[test]
+fn paused_time_is_not_counted() {
+    let clock = Arc::new(ManualClock::new());
+    let paused = Arc::new(AtomicBool::new(false));
+    let (tx, rx) = mpsc::channel();
+    let resuming = ResumingClock {
+        clock: Arc::clone(&clock),
+        controls: tx.clone(),
+        paused: Arc::clone(&paused),
+        pause_for: Duration::from_secs(10 * MIN),
+        paused_since: Mutex::new(None),
+    };
+
+    let mut events = Vec::new();
+    let outcome = Session::new(config(1, 4))
+        .with_clock(resuming)
+        .run(&rx, &mut |event: &Event| {
+            // Pause once, halfway through the focus phase
+            if let Event::Tick {
+                remaining,
+                paused: false,
+                ..
+            } = *event
+                && remaining == 750
+                && !events
+                    .iter()
+                    .any(|e| matches!(e, Event::Tick { paused: true, .. }))
+            {
+                paused.store(true, Ordering::SeqCst);
+                tx.send(Control::TogglePause).unwrap();
+            }
+            events.push(event.clone());
+        });
+
+    assert_eq!(outcome, Outcome::Completed);
+    // The display froze while paused...
+    assert!(events.contains(&Event::Tick {
+        cycle: 1,
+        phase: Phase::Focus,
+        remaining: 750,
+        total: 25 * MIN,
+        paused: true,
+    }));
+    // ...the phase still counted exactly 25 minutes of focus...
+    assert_eq!(
+        completed(&events),
+        vec![(Phase::Focus, Outcome::Completed, 25 * MIN)]
+    );
+    // ...and the wall clock moved on by the extra paused time
+    let total = clock.elapsed().as_secs();
+    assert!((35 * MIN..35 * MIN + 2).contains(&total), "took {total}s");
+}
+
+#[test]
+fn cancel_flag_stops_the_session() {
+    let clock = Arc::new(ManualClock::new());
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (_tx, rx) = mpsc::channel();
+    let mut events = Vec::new();
+    let outcome = Session::new(config(4, 4))
+        .with_clock(Arc::clone(&clock))
+        .with_cancel_flag(Arc::clone(&cancelled))
+        .run(&rx, &mut |event: &Event| {
+            if let Event::Tick {
+                phase: Phase::Break,
+                remaining: 60,
+                ..
+            } = *event
+            {
+                cancelled.store(true, Ordering::SeqCst);
+            }
+            events.push(event.clone());
+        });
+
+    assert_eq!(outcome, Outcome::Cancelled);
+    assert_eq!(
+        events.last(),
+        Some(&Event::Cancelled {
+            cycle: 1,
+            phase: Phase::Break,
+            elapsed: 4 * MIN,
+        })
+    );
+    assert_eq!(clock.elapsed(), Duration::from_secs(29 * MIN));
+}
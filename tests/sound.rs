@@ -0,0 +1,111 @@
+// Sounds at phase boundaries, heard through the file-sink backend
+
+use pomodoro_cli::sound::{Cue, FileSink, Sound};
+use pomodoro_cli::{ConfigFile, Control, ManualClock, Session, SessionConfig, SoundPlayer, Sounds};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+// A fresh file path inside a per-test temporary directory
+fn temp_file(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pomodoro-sound-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.join("sounds.log")
+}
+
+#[test]
+fn sounds_section_picks_a_sound_per_cue() {
+    let file = ConfigFile::parse(
+        r#"
+        [sounds]
+        break-end = "/usr/share/sounds/gong.ogg"
+        session-complete = ""
+        volume = 150
+        ring = "loud"
+        "#,
+    )
+    .unwrap();
+    let sounds = &file.sounds;
+    assert_eq!(sounds.sound(Cue::FocusEnd), Some(Sound::Bell));
+    assert_eq!(
+        sounds.sound(Cue::BreakEnd),
+        Some(Sound::File("/usr/share/sounds/gong.ogg".into()))
+    );
+    assert_eq!(sounds.sound(Cue::SessionComplete), None);
+    assert_eq!(sounds.sound(Cue::Tick), None);
+    assert_eq!(sounds.volume(), 100);
+    assert_eq!(file.unknown_keys(), vec!["sounds.ring"]);
+}
+
+#[test]
+fn cues_play_at_phase_ends_and_ticks_during_focus() {
+    let sink = temp_file("session");
+    let sounds = Sounds {
+        tick: Some("tick.wav".to_string()),
+        volume: Some(40),
+        ..Sounds::default()
+    };
+    let config = SessionConfig {
+        focus_secs: 2,
+        break_secs: 1,
+        long_break_secs: 1,
+        cycles: 2,
+        long_every: 4,
+    };
+    let (_tx, rx) = mpsc::channel();
+    let mut player = SoundPlayer::new(sounds, Box::new(FileSink::new(&sink)), 2);
+    Session::new(config)
+        .with_clock(ManualClock::new())
+        .run(&rx, &mut player);
+    drop(player);
+
+    // The last focus phase ends the session, so it gets that sound instead
+    assert_eq!(
+        fs::read_to_string(&sink).unwrap(),
+        "tick tick.wav 40\n\
+         tick tick.wav 40\n\
+         focus-end bell 40\n\
+         break-end bell 40\n\
+         tick tick.wav 40\n\
+         tick tick.wav 40\n\
+         session-complete bell 40\n"
+    );
+}
+
+#[test]
+fn the_session_end_sounds_however_the_last_focus_phase_ends() {
+    let config = SessionConfig {
+        focus_secs: 2,
+        break_secs: 1,
+        long_break_secs: 1,
+        cycles: 1,
+        long_every: 4,
+    };
+
+    // Skipping the last focus phase still ends the session
+    let sink = temp_file("skipped");
+    let (tx, rx) = mpsc::channel();
+    tx.send(Control::Skip).unwrap();
+    let mut player = SoundPlayer::new(Sounds::default(), Box::new(FileSink::new(&sink)), 1);
+    Session::new(config.clone())
+        .with_clock(ManualClock::new())
+        .run(&rx, &mut player);
+    assert_eq!(
+        fs::read_to_string(&sink).unwrap(),
+        "session-complete bell 100\n"
+    );
+
+    // When the session waits on the user after the last pomodoro, it rings first
+    let sink = temp_file("asking");
+    let (_tx, rx) = mpsc::channel();
+    let mut player = SoundPlayer::new(Sounds::default(), Box::new(FileSink::new(&sink)), 1)
+        .with_last_focus_end();
+    Session::new(config)
+        .with_clock(ManualClock::new())
+        .run(&rx, &mut player);
+    assert_eq!(
+        fs::read_to_string(&sink).unwrap(),
+        "focus-end bell 100\nsession-complete bell 100\n"
+    );
+}
@@ -0,0 +1,213 @@
+// Saving the live session state and resuming from it
+
+use chrono::{Duration, Local};
+use pomodoro_cli::stats::{self, DateRange};
+use pomodoro_cli::{
+    Event, Fanout, HistoryRecorder, HistoryStore, Labels, ManualClock, Observer, Outcome, Phase,
+    Position, Session, SessionConfig, SessionState, StateFile, StateSaver, TimeFormat,
+};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, mpsc};
+
+const MIN: u64 = 60;
+
+fn config() -> SessionConfig {
+    SessionConfig {
+        focus_secs: 25 * MIN,
+        break_secs: 5 * MIN,
+        long_break_secs: 15 * MIN,
+        cycles: 2,
+        long_every: 4,
+    }
+}
+
+// A snapshot taken ten minutes into the first focus phase
+fn snapshot(paused: bool) -> SessionState {
+    SessionState {
+        pid: 1,
+        saved_at: Local::now(),
+        config: config(),
+        position: Position {
+            cycle: 1,
+            phase: Phase::Focus,
+            elapsed: 10 * MIN,
+            total: 25 * MIN,
+            reported: 0,
+        },
+        paused,
+        stopped: false,
+        profile: None,
+        time_format: TimeFormat::Clock,
+        task: None,
+        task_id: None,
+        labels: Labels::default(),
+    }
+}
+
+// A fresh state file path inside a per-test temporary directory
+fn temp_state(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pomodoro-state-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir.join("session.json")
+}
+
+#[test]
+fn time_away_is_caught_up_across_phases() {
+    let state = snapshot(false);
+    let at = |mins: i64| state.position_at(state.saved_at + Duration::minutes(mins));
+
+    // Still inside the same focus phase
+    assert_eq!(
+        at(5),
+        Some(Position {
+            elapsed: 15 * MIN,
+            ..state.position
+        })
+    );
+    // 15 minutes finish the focus phase, 2 more are spent in the break
+    assert_eq!(
+        at(17),
+        Some(Position {
+            cycle: 1,
+            phase: Phase::Break,
+            elapsed: 2 * MIN,
+            total: 5 * MIN,
+            reported: 0,
+        })
+    );
+    // The whole remaining schedule is 15 + 5 + 25 minutes
+    assert_eq!(at(44).map(|p| (p.cycle, p.phase)), Some((2, Phase::Focus)));
+    assert_eq!(at(45), None);
+}
+
+#[test]
+fn a_paused_session_resumes_where_it_stopped() {
+    let state = snapshot(true);
+    let later = state.saved_at + Duration::hours(3);
+    assert_eq!(state.position_at(later), Some(state.position));
+}
+
+#[test]
+fn saver_keeps_the_state_until_the_session_completes() {
+    let path = temp_state("saver");
+    let file = StateFile::new(&path);
+    let mut cfg = config();
+    cfg.focus_secs = 3;
+    cfg.cycles = 1;
+
+    // Cancel the session after two seconds and look at what was left behind
+    let (_tx, rx) = mpsc::channel();
+    let mut saver = StateSaver::new(file.clone(), cfg.clone(), None, TimeFormat::Compact);
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let outcome = Session::new(cfg.clone())
+        .with_clock(ManualClock::new())
+        .with_cancel_flag(Arc::clone(&cancelled))
+        .run(&rx, &mut |event: &Event| {
+            saver.on_event(event);
+            if let Event::Tick { remaining: 1, .. } = event {
+                cancelled.store(true, Ordering::SeqCst);
+            }
+        });
+    assert_eq!(outcome, Outcome::Cancelled);
+
+    let state = file.load().unwrap().expect("state was saved");
+    assert_eq!(state.pid, std::process::id());
+    assert_eq!(state.config, cfg);
+    assert_eq!(state.time_format, TimeFormat::Compact);
+    assert_eq!(
+        state.position,
+        Position {
+            cycle: 1,
+            phase: Phase::Focus,
+            elapsed: 2,
+            total: 3,
+            reported: 0,
+        }
+    );
+    assert!(!state.is_running(), "our own pid does not count as running");
+
+    // Resuming from there runs the last second and then clears the state
+    let mut saver = StateSaver::new(file.clone(), cfg.clone(), None, TimeFormat::Compact);
+    let mut events = Vec::new();
+    let outcome = Session::new(cfg)
+        .starting_at(state.position)
+        .with_clock(ManualClock::new())
+        .run(&rx, &mut |event: &Event| {
+            saver.on_event(event);
+            events.push(event.clone());
+        });
+    assert_eq!(outcome, Outcome::Completed);
+    assert!(events.contains(&Event::PhaseCompleted {
+        cycle: 1,
+        phase: Phase::Focus,
+        outcome: Outcome::Completed,
+        elapsed: 3,
+        reason: None,
+    }));
+    assert_eq!(file.load().unwrap(), None);
+}
+
+#[test]
+fn a_cancelled_and_resumed_pomodoro_counts_once_in_the_history() {
+    let path = temp_state("history");
+    let file = StateFile::new(&path);
+    let store = HistoryStore::new(path.with_file_name("history.jsonl"));
+    let mut cfg = config();
+    cfg.focus_secs = 3;
+    cfg.cycles = 1;
+    let observers = || {
+        let mut observers = Fanout::new();
+        observers.push(HistoryRecorder::new(store.clone(), None));
+        observers.push(StateSaver::new(
+            file.clone(),
+            cfg.clone(),
+            None,
+            TimeFormat::Compact,
+        ));
+        observers
+    };
+
+    // Cancelled two seconds in, as Ctrl+C does
+    let (_tx, rx) = mpsc::channel();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let mut first = observers();
+    let flag = Arc::clone(&cancelled);
+    first.push(move |event: &Event| {
+        if let Event::Tick { remaining: 1, .. } = event {
+            flag.store(true, Ordering::SeqCst);
+        }
+    });
+    let outcome = Session::new(cfg.clone())
+        .with_clock(ManualClock::new())
+        .with_cancel_flag(cancelled)
+        .run(&rx, &mut first);
+    assert_eq!(outcome, Outcome::Cancelled);
+
+    // `pomodoro resume` runs the last second
+    let state = file.load().unwrap().expect("state was saved");
+    let position = state.position_at(Local::now()).unwrap();
+    let outcome = Session::new(cfg.clone())
+        .starting_at(position)
+        .with_clock(ManualClock::new())
+        .run(&rx, &mut observers());
+    assert_eq!(outcome, Outcome::Completed);
+
+    let records = store.load().unwrap();
+    let parts: Vec<_> = records
+        .iter()
+        .map(|r| (r.outcome, r.actual_secs, r.resumed))
+        .collect();
+    assert_eq!(
+        parts,
+        [
+            (Outcome::Cancelled, 2, false),
+            (Outcome::Completed, 1, true)
+        ]
+    );
+    let report = stats::report(&records, DateRange::day(stats::today()), &Labels::default());
+    assert_eq!((report.completed, report.attempted), (1, 1));
+    assert_eq!(report.focus_secs, 3);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,288 @@
+// Reports computed from the session history
+
+use chrono::{Local, NaiveDate, TimeZone};
+use pomodoro_cli::stats::{self, DateRange, GroupBy};
+use pomodoro_cli::{Interruption, InterruptionRecord, Labels, Outcome, Phase, PhaseRecord};
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+// A focus (or break) record starting at the given local day and hour
+fn record(day: u32, hour: u32, phase: Phase, outcome: Outcome, pauses: u32) -> PhaseRecord {
+    let started_at = Local.with_ymd_and_hms(2026, 10, day, hour, 0, 0).unwrap();
+    let actual_secs = if outcome == Outcome::Completed {
+        1500
+    } else {
+        600
+    };
+    PhaseRecord {
+        started_at,
+        ended_at: started_at + chrono::Duration::seconds(actual_secs as i64),
+        phase,
+        cycle: 1,
+        planned_secs: 1500,
+        actual_secs,
+        resumed: false,
+        outcome,
+        reason: None,
+        pauses,
+        profile: None,
+        task: None,
+        task_id: None,
+        labels: Labels::default(),
+        interruptions: Vec::new(),
+        rating: None,
+        note: None,
+    }
+}
+
+#[test]
+fn periods() {
+    // 2026-10-14 is a Wednesday
+    let wednesday = date(2026, 10, 14);
+    assert_eq!(
+        DateRange::day(wednesday),
+        DateRange::parse("2026-10-14").unwrap()
+    );
+    assert_eq!(
+        DateRange::week(wednesday),
+        DateRange::parse("2026-10-12..2026-10-18").unwrap()
+    );
+    assert_eq!(
+        DateRange::month(wednesday),
+        DateRange::parse("2026-10-01..2026-10-31").unwrap()
+    );
+    assert_eq!(DateRange::month(date(2028, 2, 10)).to, date(2028, 2, 29));
+    assert!(DateRange::parse("2026-10-18..2026-10-12").is_err());
+    assert!(DateRange::parse("yesterday").is_err());
+}
+
+#[test]
+fn report_totals() {
+    use Outcome::*;
+    let records = vec![
+        record(12, 9, Phase::Focus, Completed, 0),
+        record(12, 9, Phase::Break, Completed, 0),
+        record(12, 10, Phase::Focus, Completed, 1),
+        record(13, 9, Phase::Focus, Skipped, 2),
+        record(13, 14, Phase::Focus, Completed, 0),
+        record(15, 9, Phase::Focus, Completed, 1),
+        record(16, 9, Phase::Focus, Cancelled, 0),
+        // Outside the range
+        record(20, 9, Phase::Focus, Completed, 0),
+    ];
+
+    let report = stats::report(
+        &records,
+        DateRange::week(date(2026, 10, 14)),
+        &Labels::default(),
+    );
+    assert_eq!(report.completed, 4);
+    assert_eq!(report.attempted, 6);
+    assert_eq!(report.focus_secs, 4 * 1500 + 2 * 600);
+    assert!((report.completion_rate - 4.0 / 6.0).abs() < 1e-9);
+    assert_eq!(report.avg_interruptions, 0.0);
+    // The 12th and 13th are consecutive; the 14th has nothing
+    assert_eq!(report.longest_streak_days, 2);
+    assert_eq!(report.best_hour, Some(9));
+    assert_eq!(report.days.len(), 4);
+    assert!(report.to_table().contains("Completed pomodoros   4"));
+}
+
+#[test]
+fn voided_pomodoros_are_reported_but_not_completed() {
+    let records = vec![
+        record(14, 9, Phase::Focus, Outcome::Voided, 0),
+        record(14, 9, Phase::Focus, Outcome::Completed, 0),
+    ];
+    let report = stats::report(
+        &records,
+        DateRange::day(date(2026, 10, 14)),
+        &Labels::default(),
+    );
+    assert_eq!(
+        (report.completed, report.voided, report.attempted),
+        (1, 1, 2)
+    );
+    assert!(report.to_table().contains("Voided pomodoros      1"));
+}
+
+#[test]
+fn a_pomodoro_resumed_across_the_range_boundary_is_attempted_once() {
+    // Cancelled late on the 13th, resumed and completed on the 14th: the day
+    // report counts the attempt there, the week report once, on the 13th
+    let resumed = PhaseRecord {
+        resumed: true,
+        ..record(14, 0, Phase::Focus, Outcome::Completed, 0)
+    };
+    let records = vec![
+        record(13, 23, Phase::Focus, Outcome::Cancelled, 0),
+        resumed,
+        PhaseRecord {
+            cycle: 2,
+            ..record(14, 9, Phase::Focus, Outcome::Completed, 0)
+        },
+    ];
+
+    let day = stats::report(
+        &records,
+        DateRange::day(date(2026, 10, 14)),
+        &Labels::default(),
+    );
+    assert_eq!((day.completed, day.attempted), (2, 2));
+    assert_eq!(day.completion_rate, 1.0);
+
+    let week = stats::report(
+        &records,
+        DateRange::week(date(2026, 10, 14)),
+        &Labels::default(),
+    )
+    .with_groups(&records, GroupBy::Task);
+    assert_eq!((week.completed, week.attempted), (2, 2));
+    assert_eq!((week.groups[0].completed, week.groups[0].attempted), (2, 2));
+}
+
+#[test]
+fn empty_history() {
+    let report = stats::report(&[], DateRange::day(date(2026, 10, 14)), &Labels::default());
+    assert_eq!(report.completed, 0);
+    assert_eq!(report.completion_rate, 0.0);
+    assert_eq!(report.best_hour, None);
+    assert!(report.to_table().contains("Best time of day      -"));
+}
+
+#[test]
+fn filter_and_group_by_project_and_tags() {
+    use Outcome::*;
+    let labelled = |project: Option<&str>, tags: &[&str], outcome| PhaseRecord {
+        labels: Labels::new(
+            project.map(String::from),
+            tags.iter().map(|tag| tag.to_string()).collect(),
+        ),
+        ..record(14, 9, Phase::Focus, outcome, 0)
+    };
+    let records = vec![
+        labelled(Some("backend"), &["review", "oncall"], Completed),
+        labelled(Some("backend"), &["review"], Completed),
+        labelled(Some("Backend"), &[], Skipped),
+        labelled(Some("docs"), &["review"], Completed),
+        labelled(None, &[], Completed),
+    ];
+    let week = DateRange::week(date(2026, 10, 14));
+
+    // Projects match ignoring case; every tag asked for must be there
+    let backend = Labels::new(Some("backend".into()), Vec::new());
+    assert_eq!(stats::report(&records, week, &backend).attempted, 3);
+    let reviews = Labels::new(None, vec![" review ".into(), "review".into()]);
+    assert_eq!(reviews.tags, ["review"]);
+    assert_eq!(stats::report(&records, week, &reviews).completed, 3);
+    let both = Labels::new(
+        Some("backend".into()),
+        vec!["review".into(), "oncall".into()],
+    );
+    assert_eq!(stats::report(&records, week, &both).completed, 1);
+
+    // A phase counts towards each of its tags, and untagged ones are grouped together
+    let by_tag =
+        stats::report(&records, week, &Labels::default()).with_groups(&records, GroupBy::Tag);
+    let groups: Vec<_> = by_tag
+        .groups
+        .iter()
+        .map(|group| (group.name.as_deref(), group.completed))
+        .collect();
+    assert_eq!(
+        groups,
+        [(Some("review"), 3), (None, 1), (Some("oncall"), 1)]
+    );
+    assert!(by_tag.to_table().contains("(none)"));
+
+    let by_project =
+        stats::report(&records, week, &reviews).with_groups(&records, GroupBy::Project);
+    assert_eq!(by_project.groups.len(), 2);
+    assert_eq!(by_project.groups[0].name.as_deref(), Some("backend"));
+    let by_project =
+        stats::report(&records, week, &Labels::default()).with_groups(&records, GroupBy::Project);
+    assert_eq!(by_project.groups[0].attempted, 3);
+    assert_eq!("task".parse::<GroupBy>(), Ok(GroupBy::Task));
+}
+
+#[test]
+fn focus_ratings_by_hour_and_task() {
+    let rated = |hour, task: Option<&str>, rating, outcome| PhaseRecord {
+        task: task.map(String::from),
+        rating: Some(rating),
+        ..record(14, hour, Phase::Focus, outcome, 0)
+    };
+    let records = vec![
+        rated(9, Some("Release notes"), 5, Outcome::Completed),
+        rated(9, Some("release notes"), 4, Outcome::Completed),
+        rated(15, None, 2, Outcome::Completed),
+        // Only completed pomodoros count
+        rated(15, Some("Inbox"), 1, Outcome::Skipped),
+        record(16, 9, Phase::Focus, Outcome::Completed, 0),
+    ];
+    let report = stats::report(
+        &records,
+        DateRange::day(date(2026, 10, 14)),
+        &Labels::default(),
+    );
+    assert_eq!(report.rated, 3);
+    assert!((report.avg_rating.unwrap() - 11.0 / 3.0).abs() < 1e-9);
+    let by_hour: Vec<_> = report
+        .rating_by_hour
+        .iter()
+        .map(|hour| (hour.hour, hour.rated, hour.avg_rating))
+        .collect();
+    assert_eq!(by_hour, [(9, 2, 4.5), (15, 1, 2.0)]);
+    let by_task: Vec<_> = report
+        .rating_by_task
+        .iter()
+        .map(|task| (task.task.as_deref(), task.avg_rating))
+        .collect();
+    assert_eq!(by_task, [(Some("Release notes"), 4.5), (None, 2.0)]);
+    assert!(
+        report
+            .to_table()
+            .contains("Avg. focus rating     3.7/5 (3 rated)")
+    );
+
+    let unrated = stats::report(&[], DateRange::day(date(2026, 10, 14)), &Labels::default());
+    assert_eq!(unrated.avg_rating, None);
+    assert!(unrated.to_table().contains("Avg. focus rating     -"));
+}
+
+#[test]
+fn interruptions_are_counted_by_kind_not_pauses() {
+    let logged = |kinds: &[Interruption], pauses| PhaseRecord {
+        interruptions: kinds
+            .iter()
+            .map(|&kind| InterruptionRecord {
+                at: Local::now(),
+                kind,
+                note: None,
+            })
+            .collect(),
+        ..record(14, 9, Phase::Focus, Outcome::Completed, pauses)
+    };
+    let records = vec![
+        logged(&[Interruption::Internal, Interruption::External], 0),
+        logged(&[Interruption::Internal], 3),
+        logged(&[], 2),
+    ];
+    let report = stats::report(
+        &records,
+        DateRange::day(date(2026, 10, 14)),
+        &Labels::default(),
+    );
+    assert_eq!(
+        (report.internal_interruptions, report.external_interruptions),
+        (2, 1)
+    );
+    assert!((report.avg_interruptions - 1.0).abs() < 1e-9);
+    assert!(
+        report
+            .to_table()
+            .contains("Avg. interruptions    1.0 (2 internal, 1 external)")
+    );
+}
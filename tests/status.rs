@@ -0,0 +1,83 @@
+// Status lines for status bars, and the status of a session saved by another process
+
+use chrono::{Duration, Local};
+use pomodoro_cli::{
+    Labels, Phase, Position, SessionConfig, SessionState, Status, StatusTemplate, TimeFormat,
+};
+
+fn status(paused: bool) -> Status {
+    Status {
+        cycle: 2,
+        cycles: 4,
+        phase: Phase::Focus,
+        remaining: 754,
+        total: 1500,
+        paused,
+    }
+}
+
+#[test]
+fn templates_fill_in_every_placeholder() {
+    let template: StatusTemplate = "{phase} {remaining} {cycle}/{cycles}".parse().unwrap();
+    assert_eq!(
+        template.render(&status(false), TimeFormat::Clock),
+        "Focus 12:34 2/4"
+    );
+
+    let template: StatusTemplate = "{{{state}}} {elapsed} of {total}, {percent}"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        template.render(&status(true), TimeFormat::Compact),
+        "{paused} 13m of 25m, 49%"
+    );
+}
+
+#[test]
+fn bad_templates_are_rejected_with_a_hint() {
+    let err = "{phase} {left}".parse::<StatusTemplate>().unwrap_err();
+    assert!(err.contains("unknown placeholder {left}"), "{err}");
+    assert!(err.contains("remaining"), "{err}");
+    assert!("{phase".parse::<StatusTemplate>().is_err());
+    assert!("phase}".parse::<StatusTemplate>().is_err());
+}
+
+#[test]
+fn saved_state_reports_the_live_status() {
+    let state = SessionState {
+        pid: 1,
+        saved_at: Local::now(),
+        config: SessionConfig {
+            cycles: 4,
+            ..SessionConfig::default()
+        },
+        position: Position {
+            cycle: 2,
+            phase: Phase::Focus,
+            elapsed: 700,
+            total: 1500,
+            reported: 0,
+        },
+        paused: false,
+        stopped: false,
+        profile: None,
+        time_format: TimeFormat::Clock,
+        task: None,
+        task_id: None,
+        labels: Labels::default(),
+    };
+
+    // The file is rewritten every second, but the status still counts on from it
+    let status = state
+        .status_at(state.saved_at + Duration::seconds(46))
+        .unwrap();
+    assert_eq!(status.remaining, 754);
+    assert_eq!((status.cycle, status.cycles), (2, 4));
+
+    // A cancelled session is no longer running anywhere
+    let stopped = SessionState {
+        stopped: true,
+        ..state
+    };
+    assert!(!stopped.is_running());
+}
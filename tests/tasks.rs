@@ -0,0 +1,115 @@
+// Tasks: the task file and the pomodoros attributed to each task
+
+use pomodoro_cli::tasks::{self, TaskError};
+use pomodoro_cli::{HistoryRecorder, HistoryStore, ManualClock, Session, SessionConfig, TaskStore};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+// A fresh directory for one test's files
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pomodoro-tasks-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn tasks_are_found_by_number_or_name() {
+    let store = TaskStore::new(temp_dir("store").join("tasks.json"));
+    assert!(store.load().unwrap().is_empty());
+
+    let login = store.add("Fix login bug", Some(3)).unwrap();
+    let docs = store.add("Write docs", None).unwrap();
+    assert_eq!((login.id, docs.id), (1, 2));
+    assert!(matches!(
+        store.add("fix LOGIN bug", None),
+        Err(TaskError::Duplicate(_))
+    ));
+
+    assert_eq!(store.get("#2").unwrap().name, "Write docs");
+    assert_eq!(store.get("write docs").unwrap().id, 2);
+    assert!(matches!(store.get("docs"), Err(TaskError::NotFound(_))));
+
+    // Once done, the name is free for a new task, which takes precedence
+    let done = store
+        .update("1", |task| task.done_at = Some(chrono::Local::now()))
+        .unwrap();
+    assert!(done.is_done());
+    let again = store.add("Fix login bug", None).unwrap();
+    assert_eq!(store.get("Fix login bug").unwrap().id, again.id);
+    assert_eq!(store.load().unwrap().len(), 3);
+}
+
+#[test]
+fn completed_focus_phases_count_towards_their_task() {
+    let dir = temp_dir("actual");
+    let store = TaskStore::new(dir.join("tasks.json"));
+    store.add("Write docs", Some(4)).unwrap();
+    let history = HistoryStore::new(dir.join("history.jsonl"));
+
+    let config = SessionConfig {
+        focus_secs: 2,
+        break_secs: 1,
+        long_break_secs: 1,
+        cycles: 2,
+        long_every: 4,
+    };
+    let (_tx, rx) = mpsc::channel();
+    let mut recorder = HistoryRecorder::new(history.clone(), None)
+        .with_task(Some("Write docs".to_string()), Some(1));
+    Session::new(config)
+        .with_clock(ManualClock::new())
+        .run(&rx, &mut recorder);
+
+    // Breaks are not attributed to the task
+    let records = history.load().unwrap();
+    let attributed: Vec<_> = records.iter().map(|r| r.task.as_deref()).collect();
+    assert_eq!(attributed, [Some("Write docs"), None, Some("Write docs")]);
+
+    let summaries = tasks::summaries(&store.load().unwrap(), &records);
+    assert_eq!((summaries[0].actual, summaries[0].estimate), (2, Some(4)));
+}
+
+#[test]
+fn a_new_task_does_not_inherit_the_pomodoros_of_a_done_one_with_its_name() {
+    let dir = temp_dir("reused");
+    let store = TaskStore::new(dir.join("tasks.json"));
+    let old = store.add("Review PRs", Some(2)).unwrap();
+    let history = HistoryStore::new(dir.join("history.jsonl"));
+    let config = SessionConfig {
+        focus_secs: 2,
+        break_secs: 1,
+        long_break_secs: 1,
+        cycles: 1,
+        long_every: 4,
+    };
+    let work_on = |id| {
+        let (_tx, rx) = mpsc::channel();
+        let mut recorder = HistoryRecorder::new(history.clone(), None)
+            .with_task(Some("Review PRs".to_string()), Some(id));
+        Session::new(config.clone())
+            .with_clock(ManualClock::new())
+            .run(&rx, &mut recorder);
+    };
+    work_on(old.id);
+    work_on(old.id);
+    store
+        .update("1", |task| task.done_at = Some(chrono::Local::now()))
+        .unwrap();
+    let new = store.add("Review PRs", Some(1)).unwrap();
+    work_on(new.id);
+
+    let records = history.load().unwrap();
+    let actual: Vec<_> = tasks::summaries(&store.load().unwrap(), &records)
+        .iter()
+        .map(|task| (task.id, task.actual))
+        .collect();
+    assert_eq!(actual, [(old.id, 2), (new.id, 1)]);
+
+    // Records without a number still count by name
+    let mut unnumbered = records[0].clone();
+    unnumbered.task_id = None;
+    let summaries = tasks::summaries(&store.load().unwrap(), &[unnumbered]);
+    assert_eq!((summaries[0].actual, summaries[1].actual), (1, 1));
+}
@@ -0,0 +1,53 @@
+// The terminal title and tab progress
+
+use pomodoro_cli::{
+    Event, ManualClock, Observer, Phase, Session, SessionConfig, TerminalTitle, TimeFormat,
+};
+use std::sync::mpsc;
+
+#[test]
+fn ticks_set_the_title_and_progress() {
+    let mut out = Vec::new();
+    {
+        let mut title = TerminalTitle::new(&mut out, 4, TimeFormat::Clock);
+        let tick = Event::Tick {
+            cycle: 2,
+            phase: Phase::Focus,
+            remaining: 750,
+            total: 1500,
+            paused: true,
+        };
+        title.on_event(&tick);
+        // The same tick again changes nothing, so nothing is written
+        title.on_event(&tick);
+    }
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "\x1b[22;0t\
+         \x1b]0;Focus 12:30 (paused) · 2/4 · pomodoro\x07\x1b]9;4;4;50\x07\
+         \x1b]9;4;0;0\x07\x1b[23;0t"
+    );
+}
+
+#[test]
+fn the_original_title_is_restored_after_a_session() {
+    let config = SessionConfig {
+        focus_secs: 2,
+        break_secs: 1,
+        long_break_secs: 1,
+        cycles: 1,
+        long_every: 4,
+    };
+    let mut out = Vec::new();
+    let (_tx, rx) = mpsc::channel();
+    {
+        let mut title = TerminalTitle::new(&mut out, 1, TimeFormat::Clock);
+        Session::new(config)
+            .with_clock(ManualClock::new())
+            .run(&rx, &mut |event: &Event| title.on_event(event));
+    }
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with("\x1b[22;0t"));
+    assert!(out.contains("Focus 0:01 · 1/1 · pomodoro\x07\x1b]9;4;1;50\x07"));
+    assert!(out.ends_with("\x1b]9;4;0;0\x07\x1b[23;0t"));
+}
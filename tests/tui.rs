@@ -0,0 +1,29 @@
+// The full-screen display's block digits
+
+use pomodoro_cli::tui::big_text;
+
+#[test]
+fn digits_are_drawn_five_rows_high() {
+    let rows = big_text("1:07", 1);
+    assert_eq!(
+        rows,
+        [
+            "  #   ### ###",
+            "  # # # #   #",
+            "  #   # #   #",
+            "  # # # #   #",
+            "  #   ###   #",
+        ]
+        .map(|row| row.replace('#', "█"))
+    );
+}
+
+#[test]
+fn scaling_widens_every_cell() {
+    let narrow = big_text("25:00", 1);
+    let wide = big_text("25:00", 2);
+    // Four 3-cell digits, a 1-cell colon and four gaps
+    assert_eq!(narrow[0].chars().count(), 17);
+    assert_eq!(wide[0].chars().count(), 30);
+    assert!(wide.iter().all(|row| row.chars().count() == 30));
+}
@@ -0,0 +1,75 @@
+// Run configuration validation: errors, exit codes and warnings
+
+use pomodoro_cli::duration::ParseDurationError;
+use pomodoro_cli::validate::{EXIT_INVALID, Field};
+use pomodoro_cli::{ConfigError, ConfigWarning, SessionConfig, parse_duration};
+
+const MIN: u64 = 60;
+
+fn config(
+    focus_secs: u64,
+    break_secs: u64,
+    long_break_secs: u64,
+    cycles: u64,
+    long_every: u64,
+) -> SessionConfig {
+    SessionConfig {
+        focus_secs,
+        break_secs,
+        long_break_secs,
+        cycles,
+        long_every,
+    }
+}
+
+#[test]
+fn defaults_are_valid_without_warnings() {
+    assert_eq!(
+        config(25 * MIN, 5 * MIN, 15 * MIN, 4, 4).validate(),
+        Ok(vec![])
+    );
+}
+
+#[test]
+fn zero_values_are_rejected() {
+    for (config, field) in [
+        (config(0, 5 * MIN, 15 * MIN, 4, 4), Field::Focus),
+        (config(25 * MIN, 5 * MIN, 15 * MIN, 0, 4), Field::Cycles),
+        (config(25 * MIN, 5 * MIN, 15 * MIN, 4, 0), Field::LongEvery),
+    ] {
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, ConfigError::Zero { field });
+        assert_eq!(err.exit_code(), EXIT_INVALID);
+    }
+}
+
+#[test]
+fn durations_too_large_for_seconds_are_rejected_when_parsed() {
+    // What `--focus` gets for plain minutes that do not fit in seconds
+    let minutes = (u64::MAX / 60 + 1).to_string();
+    let err = parse_duration(&minutes).unwrap_err();
+    assert_eq!(err, ParseDurationError::Overflow);
+    assert_eq!(err.to_string(), "duration is too large");
+    assert!(parse_duration(&format!("{}m", u64::MAX / 60)).is_ok());
+}
+
+#[test]
+fn odd_combinations_only_warn() {
+    assert_eq!(
+        config(25 * MIN, 5 * MIN, 15 * MIN, 2, 3).validate(),
+        Ok(vec![ConfigWarning::LongBreakNeverReached {
+            long_every: 3,
+            cycles: 2
+        }])
+    );
+    assert_eq!(
+        config(25 * MIN, 10 * MIN, 5 * MIN, 8, 4).validate(),
+        Ok(vec![ConfigWarning::LongBreakShorterThanBreak])
+    );
+    assert_eq!(
+        config(25 * MIN, 0, 15 * MIN, 4, 4).validate(),
+        Ok(vec![ConfigWarning::ZeroBreak {
+            field: Field::Break
+        }])
+    );
+}